@@ -0,0 +1,249 @@
+use std::borrow::Cow;
+use std::str::FromStr;
+use encoding_rs::Encoding;
+use chardetng::EncodingDetector;
+use zip_parser::{ flag, extra, Zip64Archive, CentralFileHeader, UnicodeExtraField };
+
+
+/// Character set of names and comments that aren't flagged as UTF-8.
+#[derive(Clone, Copy, Debug)]
+pub enum Charset {
+    /// detect it once per archive, from every name that isn't valid UTF-8.
+    Auto,
+    /// IBM code page 437, the default of the ZIP specification.
+    Cp437,
+    Encoding(&'static Encoding),
+}
+
+impl FromStr for Charset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Charset, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "auto" => Ok(Charset::Auto),
+            "cp437" | "ibm437" | "437" => Ok(Charset::Cp437),
+            label => Encoding::for_label(label.as_bytes())
+                .map(Charset::Encoding)
+                .ok_or_else(|| format!("invalid encoding label `{}`", s))
+        }
+    }
+}
+
+/// Decodes the names and comments of one archive, see APPNOTE 4.4.4 and appendix D.
+///
+/// in order, text is taken from
+/// 1. the raw field, if general purpose bit 11 (EFS) is set,
+/// 2. a Unicode Path/Comment extra field whose crc32 matches the raw field,
+/// 3. the raw field, if it is valid UTF-8 and the charset is auto-detected,
+/// 4. the raw field, decoded with the fallback charset.
+pub struct TextDecoder {
+    fallback: Fallback,
+    /// the fallback was detected from names of this archive.
+    detected: bool,
+}
+
+#[derive(Clone, Copy)]
+enum Fallback {
+    Utf8Or(&'static Encoding),
+    Cp437,
+    Encoding(&'static Encoding),
+}
+
+impl TextDecoder {
+    pub fn new(charset: Charset, zip: &Zip64Archive) -> anyhow::Result<TextDecoder> {
+        let fallback = match charset {
+            Charset::Cp437 => Fallback::Cp437,
+            Charset::Encoding(encoding) => Fallback::Encoding(encoding),
+            Charset::Auto => {
+                let mut detector = EncodingDetector::new();
+                let mut detected = false;
+
+                // a broken central directory is reported when the entries are processed
+                for cfh in zip.entries()?.map_while(Result::ok) {
+                    if unicode(&cfh, cfh.name, extra::UNICODE_PATH).is_none()
+                        && std::str::from_utf8(cfh.name).is_err()
+                    {
+                        detector.feed(cfh.name, false);
+                        detector.feed(b"\n", false);
+                        detected = true;
+                    }
+                }
+
+                if !detected {
+                    return Ok(TextDecoder { fallback: Fallback::Utf8Or(encoding_rs::UTF_8), detected })
+                }
+                detector.feed(b"", true);
+                Fallback::Utf8Or(detector.guess(None, false))
+            }
+        };
+
+        let detected = matches!(charset, Charset::Auto);
+        Ok(TextDecoder { fallback, detected })
+    }
+
+    /// the charset names and comments fall back to, if it was detected.
+    pub fn detected(&self) -> Option<&'static str> {
+        match self.fallback {
+            Fallback::Utf8Or(encoding) if self.detected => Some(encoding.name()),
+            _ => None
+        }
+    }
+
+    pub fn name<'a>(&self, cfh: &CentralFileHeader<'a>) -> Cow<'a, str> {
+        unicode(cfh, cfh.name, extra::UNICODE_PATH)
+            .unwrap_or_else(|| self.fallback(cfh.name))
+    }
+
+    pub fn comment<'a>(&self, cfh: &CentralFileHeader<'a>) -> Cow<'a, str> {
+        unicode(cfh, cfh.comment, extra::UNICODE_COMMENT)
+            .unwrap_or_else(|| self.fallback(cfh.comment))
+    }
+
+    /// the encoding `name` decodes the name of an entry with.
+    pub fn name_encoding(&self, cfh: &CentralFileHeader) -> &'static str {
+        if unicode(cfh, cfh.name, extra::UNICODE_PATH).is_some() {
+            return encoding_rs::UTF_8.name()
+        }
+        match self.fallback {
+            Fallback::Utf8Or(_) if std::str::from_utf8(cfh.name).is_ok() => encoding_rs::UTF_8.name(),
+            Fallback::Utf8Or(encoding) | Fallback::Encoding(encoding) => encoding.name(),
+            Fallback::Cp437 => "IBM437",
+        }
+    }
+
+    /// the archive comment has no flag or extra field, only the fallback applies.
+    pub fn archive_comment<'a>(&self, zip: &'a Zip64Archive) -> Cow<'a, str> {
+        self.fallback(zip.eocdr().comment)
+    }
+
+    fn fallback<'a>(&self, raw: &'a [u8]) -> Cow<'a, str> {
+        match self.fallback {
+            Fallback::Utf8Or(encoding) => match std::str::from_utf8(raw) {
+                Ok(text) => Cow::Borrowed(text),
+                Err(_) => encoding.decode(raw).0
+            },
+            Fallback::Cp437 => cp437(raw),
+            Fallback::Encoding(encoding) => encoding.decode(raw).0,
+        }
+    }
+}
+
+/// text from the raw field if it's flagged as UTF-8, or from a matching unicode extra field.
+fn unicode<'a>(cfh: &CentralFileHeader<'a>, raw: &'a [u8], id: u16) -> Option<Cow<'a, str>> {
+    if cfh.gp_flag & flag::EFS != 0 {
+        return Some(String::from_utf8_lossy(raw))
+    }
+
+    cfh.extra_fields()
+        .map_while(Result::ok)
+        .filter(|field| field.id == id)
+        .filter_map(|field| UnicodeExtraField::parse(field.data).ok())
+        // a mismatch means the raw field was changed by a tool unaware of the extra field
+        .filter(|field| field.version == 1 && field.crc32 == crc32fast::hash(raw))
+        .find_map(|field| std::str::from_utf8(field.text).ok())
+        .map(Cow::Borrowed)
+}
+
+fn cp437(raw: &[u8]) -> Cow<'_, str> {
+    const HIGH: &str = "\
+        ÇüéâäàåçêëèïîìÄÅÉæÆôöòûùÿÖÜ¢£¥₧ƒ\
+        áíóúñÑªº¿⌐¬½¼¡«»░▒▓│┤╡╢╖╕╣║╗╝╜╛┐\
+        └┴┬├─┼╞╟╚╔╩╦╠═╬╧╨╤╥╙╘╒╓╫╪┘┌█▄▌▐▀\
+        αßΓπΣσµτΦΘΩδ∞φε∩≡±≥≤⌠⌡÷≈°∙·√ⁿ²■\u{a0}";
+
+    if raw.is_ascii() {
+        // ascii is valid utf8
+        return Cow::Borrowed(std::str::from_utf8(raw).unwrap())
+    }
+
+    let high: Vec<char> = HIGH.chars().collect();
+    raw.iter()
+        .map(|&b| if b < 0x80 { char::from(b) } else { high[usize::from(b - 0x80)] })
+        .collect::<String>()
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testdata::{ self, Entry };
+
+    /// the names of `entries` and what they were decoded with.
+    fn names(charset: Charset, entries: &[Entry]) -> Vec<(String, &'static str)> {
+        let buf = testdata::zip(entries);
+        let zip = Zip64Archive::parse(&buf).unwrap();
+        let text = TextDecoder::new(charset, &zip).unwrap();
+        zip.entries().unwrap()
+            .map(|cfh| {
+                let cfh = cfh.unwrap();
+                (text.name(&cfh).into_owned(), text.name_encoding(&cfh))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn efs_flag() {
+        let entries = [Entry::new("caf\u{e9}.txt", "").flag(flag::EFS)];
+        // the flag wins over any charset
+        assert_eq!(names(Charset::Cp437, &entries), [("caf\u{e9}.txt".into(), "UTF-8")]);
+        assert_eq!(names(Charset::Encoding(encoding_rs::SHIFT_JIS), &entries), [("caf\u{e9}.txt".into(), "UTF-8")]);
+    }
+
+    #[test]
+    fn unicode_path_field() {
+        // `\x81` is ü in CP437
+        let raw = b"\x81ber.txt";
+        let entries = [
+            Entry::new(&raw[..], "").extra(extra::UNICODE_PATH, &testdata::unicode_field(raw, "\u{fc}ber.txt")),
+            // written for another name, the raw name was changed after
+            Entry::new(&raw[..], "").extra(extra::UNICODE_PATH, &testdata::unicode_field(b"uber.txt", "\u{fb}ber.txt")),
+        ];
+        assert_eq!(names(Charset::Cp437, &entries), [
+            ("\u{fc}ber.txt".into(), "UTF-8"),
+            ("\u{fc}ber.txt".into(), "IBM437"),
+        ]);
+    }
+
+    #[test]
+    fn cp437_high_bytes() {
+        let entries = [
+            Entry::new(b"\x80\x9c\xe1\xff".to_vec(), ""),
+            Entry::new("plain.txt", ""),
+        ];
+        assert_eq!(names(Charset::Cp437, &entries), [
+            ("\u{c7}\u{a3}\u{df}\u{a0}".into(), "IBM437"),
+            ("plain.txt".into(), "IBM437"),
+        ]);
+        assert_eq!(cp437(b"\xb0\xdb\xfe"), "\u{2591}\u{2588}\u{25a0}");
+    }
+
+    #[test]
+    fn detected_once_per_archive() {
+        let sjis = ["日本語のファイル名.txt", "報告書/第三四半期の売上.csv", "写真/東京タワー.jpg"];
+        let mut entries: Vec<Entry> = sjis.iter()
+            .map(|name| Entry::new(encoding_rs::SHIFT_JIS.encode(name).0.into_owned(), ""))
+            .collect();
+        entries.push(Entry::new("readme.txt", ""));
+        // valid UTF-8 stays UTF-8
+        entries.push(Entry::new("caf\u{e9}.txt", ""));
+
+        let buf = testdata::zip(&entries);
+        let zip = Zip64Archive::parse(&buf).unwrap();
+        let text = TextDecoder::new(Charset::Auto, &zip).unwrap();
+        assert_eq!(text.detected(), Some("Shift_JIS"));
+
+        let mut expect: Vec<(String, &str)> = sjis.iter().map(|name| (name.to_string(), "Shift_JIS")).collect();
+        expect.push(("readme.txt".into(), "UTF-8"));
+        expect.push(("caf\u{e9}.txt".into(), "UTF-8"));
+        assert_eq!(names(Charset::Auto, &entries), expect);
+    }
+
+    #[test]
+    fn nothing_to_detect() {
+        let buf = testdata::zip(&[Entry::new("a.txt", ""), Entry::new("caf\u{e9}.txt", "")]);
+        let zip = Zip64Archive::parse(&buf).unwrap();
+        let text = TextDecoder::new(Charset::Auto, &zip).unwrap();
+        // every name is UTF-8
+        assert_eq!(text.detected(), None);
+    }
+}
@@ -0,0 +1,264 @@
+use std::fmt::Write as _;
+use std::io::{ self, Write };
+use std::sync::Arc;
+use arrow_array::{ ArrayRef, RecordBatch };
+use arrow_array::array::{
+    BooleanArray, Date32Array, DictionaryArray, Int64Array, StringArray,
+    TimestampMillisecondArray, UInt64Array,
+};
+use arrow_array::builder::{ ListBuilder, StringBuilder, StringDictionaryBuilder };
+use arrow_array::types::Int32Type;
+use arrow_schema::{ ArrowError, DataType, Field, Schema, SchemaRef, TimeUnit };
+use arrow_ipc::writer::StreamWriter;
+use arrow_select::concat::concat_batches;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use time::Date;
+use crate::sec::{ SecData, SecFilings, History, Row };
+
+
+/// rows of a parquet row group, bounding what's buffered before it's written out.
+const ROW_GROUP_ROWS: usize = 128 * 1024;
+
+/// rows of an arrow record batch, companies are gathered until there are as many.
+const BATCH_ROWS: usize = 64 * 1024;
+
+/// julian day of 1970-01-01, date32 counts days from it.
+const UNIX_EPOCH_JULIAN_DAY: i32 = 2_440_588;
+
+fn dictionary() -> DataType {
+    DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8))
+}
+
+/// the schema of the filings table, the same for every company.
+pub fn schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("cik", DataType::UInt64, false),
+        Field::new("name", DataType::Utf8, false),
+        Field::new("tickers", DataType::List(Arc::new(Field::new("item", DataType::Utf8, true))), false),
+        Field::new("sic", DataType::Utf8, false),
+        Field::new("entity_type", dictionary(), false),
+        Field::new("accession_number", DataType::Utf8, false),
+        Field::new("filing_date", DataType::Date32, false),
+        Field::new("report_date", DataType::Date32, true),
+        Field::new("acceptance_date_time", DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into())), false),
+        Field::new("act", dictionary(), false),
+        Field::new("form", dictionary(), false),
+        Field::new("file_number", DataType::Utf8, false),
+        Field::new("items", DataType::Utf8, false),
+        Field::new("size", DataType::Int64, true),
+        Field::new("is_xbrl", DataType::Boolean, false),
+        Field::new("is_inline_xbrl", DataType::Boolean, false),
+        Field::new("primary_document", DataType::Utf8, false),
+        Field::new("primary_doc_description", DataType::Utf8, false),
+    ]))
+}
+
+fn date32(date: Date) -> i32 {
+    date.to_julian_day() - UNIX_EPOCH_JULIAN_DAY
+}
+
+/// the filings in `rows` of a company's history as a record batch of `schema`.
+///
+/// the arrays are built from the columns of the history, not from its filings row by row.
+pub fn batch<'a, 'b>(schema: &SchemaRef, company: &SecData, history: &History<'a, 'b>, rows: &[Row])
+    -> Result<RecordBatch, ArrowError>
+{
+    let len = rows.len();
+    let repeat = |value: &str| -> ArrayRef {
+        Arc::new(StringArray::from_iter_values(std::iter::repeat_n(value, len)))
+    };
+    let strings = |column: fn(&'a SecFilings<'b>) -> &'a [&'b str]| -> ArrayRef {
+        Arc::new(StringArray::from_iter_values(history.column(rows, column)))
+    };
+
+    let mut tickers = ListBuilder::new(StringBuilder::new());
+    for _ in 0..len {
+        tickers.append_value(company.tickers.iter().map(|ticker| Some(*ticker)));
+    }
+    // accession numbers and forms are formatted in place, without a string per row
+    let mut accession_numbers = StringBuilder::with_capacity(len, len * 20);
+    for accession_number in history.column(rows, |part| &part.accession_number) {
+        write!(accession_numbers, "{}", accession_number).expect("write to a string builder");
+        accession_numbers.append_value("");
+    }
+    let mut forms = StringDictionaryBuilder::<Int32Type>::new();
+    let mut form = String::new();
+    for value in history.column(rows, |part| &part.form) {
+        form.clear();
+        write!(form, "{}", value).expect("write to a string");
+        forms.append_value(&form);
+    }
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(UInt64Array::from_value(company.cik.0, len)),
+        repeat(&company.name),
+        Arc::new(tickers.finish()),
+        repeat(company.sic),
+        Arc::new(std::iter::repeat_n(company.entity_type, len).collect::<DictionaryArray<Int32Type>>()),
+        Arc::new(accession_numbers.finish()),
+        Arc::new(Date32Array::from_iter_values(history.column(rows, |part| &part.filing_date).map(|&date| date32(date)))),
+        Arc::new(history.column(rows, |part| &part.report_date).map(|date| date.map(date32)).collect::<Date32Array>()),
+        Arc::new(TimestampMillisecondArray::from_iter_values(history.column(rows, |part| &part.acceptance_date_time)
+            .map(|time| (time.unix_timestamp_nanos() / 1_000_000) as i64))
+            .with_timezone("UTC")),
+        Arc::new(history.column(rows, |part| &part.act).copied().collect::<DictionaryArray<Int32Type>>()),
+        Arc::new(forms.finish()),
+        strings(|part| &part.file_number),
+        strings(|part| &part.items),
+        Arc::new(history.column(rows, |part| &part.size).copied().collect::<Int64Array>()),
+        Arc::new(history.column(rows, |part| &part.is_xbrl).map(|&value| Some(value != 0)).collect::<BooleanArray>()),
+        Arc::new(history.column(rows, |part| &part.is_inline_xbrl).map(|&value| Some(value != 0)).collect::<BooleanArray>()),
+        strings(|part| &part.primary_document),
+        Arc::new(StringArray::from_iter_values(history.column(rows, |part| &part.primary_doc_description))),
+    ];
+
+    RecordBatch::try_new(schema.clone(), columns)
+}
+
+/// Writes the record batches of every company into one columnar file.
+pub enum Writer {
+    /// the parquet writer starts a new row group every `ROW_GROUP_ROWS` rows.
+    Parquet(ArrowWriter<Box<dyn Write + Send>>),
+    /// an arrow IPC stream, it allows the dictionaries to differ from one batch to the next.
+    Arrow {
+        writer: StreamWriter<Box<dyn Write + Send>>,
+        pending: Vec<RecordBatch>,
+        rows: usize,
+    },
+}
+
+impl Writer {
+    pub fn parquet(out: Box<dyn Write + Send>, schema: SchemaRef) -> io::Result<Writer> {
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .set_max_row_group_size(ROW_GROUP_ROWS)
+            .build();
+        ArrowWriter::try_new(out, schema, Some(properties))
+            .map(Writer::Parquet)
+            .map_err(io::Error::other)
+    }
+
+    pub fn arrow(out: Box<dyn Write + Send>, schema: &SchemaRef) -> io::Result<Writer> {
+        let writer = StreamWriter::try_new(out, schema).map_err(io::Error::other)?;
+        Ok(Writer::Arrow { writer, pending: Vec::new(), rows: 0 })
+    }
+
+    pub fn write(&mut self, batch: RecordBatch) -> io::Result<()> {
+        match self {
+            Writer::Parquet(writer) => writer.write(&batch).map_err(io::Error::other),
+            Writer::Arrow { writer, pending, rows } => {
+                *rows += batch.num_rows();
+                pending.push(batch);
+                if *rows < BATCH_ROWS {
+                    return Ok(())
+                }
+                let batch = concat_batches(&pending[0].schema(), pending.iter()).map_err(io::Error::other)?;
+                pending.clear();
+                *rows = 0;
+                writer.write(&batch).map_err(io::Error::other)
+            }
+        }
+    }
+
+    /// write what's pending and the footer, and flush.
+    pub fn finish(self) -> io::Result<()> {
+        match self {
+            Writer::Parquet(mut writer) => {
+                writer.finish().map_err(io::Error::other)?;
+                writer.inner_mut().flush()
+            },
+            Writer::Arrow { mut writer, pending, .. } => {
+                if let Some(first) = pending.first() {
+                    let batch = concat_batches(&first.schema(), pending.iter()).map_err(io::Error::other)?;
+                    writer.write(&batch).map_err(io::Error::other)?;
+                }
+                writer.into_inner().map_err(io::Error::other)?.flush()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use arrow_array::Array;
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::{ Date32Type, TimestampMillisecondType, UInt64Type };
+    use camino::Utf8Path as Path;
+    use time::Month;
+    use arrow_ipc::reader::StreamReader;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use crate::sec;
+    use crate::testdata;
+
+    /// write the filings of the test company to `path`, all of them and then only the second.
+    fn write(writer: impl FnOnce(Box<dyn Write + Send>, SchemaRef) -> Writer, path: &Path) {
+        let mut data = testdata::COMPANY.as_bytes().to_vec();
+        let company = testdata::company(&mut data);
+        let history = sec::history(&company.filings.recent, &[]).unwrap();
+
+        let schema = schema();
+        let mut writer = writer(Box::new(fs::File::create(path).unwrap()), schema.clone());
+        writer.write(batch(&schema, &company, &history, &history.rows).unwrap()).unwrap();
+        writer.write(batch(&schema, &company, &history, &history.rows[1..]).unwrap()).unwrap();
+        writer.finish().unwrap();
+    }
+
+    fn check(batch: &RecordBatch) {
+        let schema = batch.schema();
+        let data_type = |name: &str| schema.field_with_name(name).unwrap().data_type().clone();
+        assert_eq!(data_type("form"), dictionary());
+        assert_eq!(data_type("act"), dictionary());
+        assert_eq!(data_type("filing_date"), DataType::Date32);
+        assert_eq!(data_type("report_date"), DataType::Date32);
+        assert_eq!(data_type("acceptance_date_time"), DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into())));
+        assert_eq!(data_type("is_xbrl"), DataType::Boolean);
+
+        assert_eq!(batch.num_rows(), 3);
+        let column = |name: &str| batch.column_by_name(name).unwrap();
+        let accession_numbers = column("accession_number").as_string::<i32>();
+        assert_eq!(accession_numbers.iter().flatten().collect::<Vec<_>>(),
+            ["0000320193-23-000106", "0001140361-23-039575", "0001140361-23-039575"]);
+        let forms = column("form").as_dictionary::<Int32Type>();
+        let forms = forms.downcast_dict::<StringArray>().unwrap();
+        assert_eq!(forms.into_iter().flatten().collect::<Vec<_>>(), ["10-K", "8-K/A", "8-K/A"]);
+        let report_dates = column("report_date").as_primitive::<Date32Type>();
+        assert_eq!(report_dates.value(0), date32(Date::from_calendar_date(2023, Month::September, 30).unwrap()));
+        assert!(report_dates.is_null(1));
+        let accepted = column("acceptance_date_time").as_primitive::<TimestampMillisecondType>();
+        assert_eq!(accepted.value(1), 1_691_692_293_000);
+        let is_xbrl = column("is_xbrl").as_boolean();
+        assert_eq!(is_xbrl.iter().flatten().collect::<Vec<_>>(), [true, false, false]);
+        assert_eq!(column("cik").as_primitive::<UInt64Type>().value(2), 320193);
+    }
+
+    #[test]
+    fn parquet_round_trip() {
+        let (_dir, dir) = testdata::temp_dir();
+        let path = dir.join("filings.parquet");
+        write(|out, schema| Writer::parquet(out, schema).unwrap(), &path);
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(fs::File::open(&path).unwrap())
+            .unwrap()
+            .build()
+            .unwrap();
+        let batches = reader.collect::<Result<Vec<_>, _>>().unwrap();
+        check(&concat_batches(&batches[0].schema(), &batches).unwrap());
+    }
+
+    #[test]
+    fn arrow_round_trip() {
+        let (_dir, dir) = testdata::temp_dir();
+        let path = dir.join("filings.arrow");
+        write(|out, schema| Writer::arrow(out, &schema).unwrap(), &path);
+
+        let reader = StreamReader::try_new(fs::File::open(&path).unwrap(), None).unwrap();
+        let batches = reader.collect::<Result<Vec<_>, _>>().unwrap();
+        // both companies are gathered into one batch
+        assert_eq!(batches.len(), 1);
+        check(&batches[0]);
+    }
+}
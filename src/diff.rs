@@ -0,0 +1,162 @@
+use std::str::FromStr;
+use std::collections::BTreeMap;
+use serde::Serialize;
+use zip_parser::{ Zip64Archive, CentralFileHeader };
+use crate::charset::TextDecoder;
+
+
+/// How `diff` prints the differences.
+#[derive(Clone, Copy, Debug)]
+pub enum Format {
+    /// one `A`, `D` or `M` and the name per line, like `git diff --name-status`.
+    Text,
+    /// one JSON object per line.
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Format, String> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            _ => Err(format!("unknown diff format `{}`, expected one of: text, json", s))
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Change {
+    Added,
+    Removed,
+    Modified,
+}
+
+impl Change {
+    pub fn letter(self) -> char {
+        match self {
+            Change::Added => 'A',
+            Change::Removed => 'D',
+            Change::Modified => 'M',
+        }
+    }
+}
+
+/// What the central directory says about the content of an entry.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+pub struct Fingerprint {
+    pub size: u64,
+    pub crc32: u32,
+}
+
+impl Fingerprint {
+    pub fn of(cfh: &CentralFileHeader) -> Fingerprint {
+        Fingerprint {
+            size: cfh.uncomp_size.into(),
+            crc32: cfh.crc32,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Difference {
+    pub change: Change,
+    pub name: String,
+    pub old: Option<Fingerprint>,
+    pub new: Option<Fingerprint>,
+}
+
+/// the file entries of an archive by decoded name, a later entry of the same name wins.
+pub fn entries<'a>(zip: &'a Zip64Archive, text: &TextDecoder)
+    -> anyhow::Result<BTreeMap<String, CentralFileHeader<'a>>>
+{
+    let mut entries = BTreeMap::new();
+    for cfh in zip.entries()? {
+        let cfh = cfh?;
+        let name = text.name(&cfh);
+        if !name.ends_with('/') {
+            entries.insert(name.into_owned(), cfh);
+        }
+    }
+    Ok(entries)
+}
+
+/// compare two archives by name, size and crc32, in name order.
+pub fn compare(old: &BTreeMap<String, CentralFileHeader>, new: &BTreeMap<String, CentralFileHeader>)
+    -> Vec<Difference>
+{
+    let mut differences = Vec::new();
+
+    for (name, cfh) in old.iter() {
+        let old_fp = Fingerprint::of(cfh);
+        let change = match new.get(name).map(Fingerprint::of) {
+            None => Some(Change::Removed),
+            Some(new_fp) if new_fp != old_fp => Some(Change::Modified),
+            Some(_) => None
+        };
+        if let Some(change) = change {
+            differences.push(Difference {
+                change,
+                name: name.clone(),
+                old: Some(old_fp),
+                new: new.get(name).map(Fingerprint::of),
+            });
+        }
+    }
+    for (name, cfh) in new.iter().filter(|(name, _)| !old.contains_key(*name)) {
+        differences.push(Difference {
+            change: Change::Added,
+            name: name.clone(),
+            old: None,
+            new: Some(Fingerprint::of(cfh)),
+        });
+    }
+
+    differences.sort_by(|a, b| a.name.cmp(&b.name));
+    differences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::charset::Charset;
+    use crate::testdata::{ self, Entry };
+
+    #[test]
+    fn changes() {
+        let old = testdata::zip(&[
+            Entry::new("same.txt", "same"),
+            Entry::new("removed.txt", "removed"),
+            Entry::new("longer.txt", "hello"),
+            Entry::new("other.txt", "hello"),
+        ]);
+        let new = testdata::zip(&[
+            Entry::new("same.txt", "same"),
+            Entry::new("longer.txt", "hello!"),
+            // same size
+            Entry::new("other.txt", "jello"),
+            Entry::new("added.txt", "added"),
+            // directories don't count
+            Entry::dir("d/"),
+        ]);
+        let old_zip = Zip64Archive::parse(&old).unwrap();
+        let new_zip = Zip64Archive::parse(&new).unwrap();
+        let old = entries(&old_zip, &TextDecoder::new(Charset::Auto, &old_zip).unwrap()).unwrap();
+        let new = entries(&new_zip, &TextDecoder::new(Charset::Auto, &new_zip).unwrap()).unwrap();
+
+        let fingerprint = |data: &str| Some(Fingerprint { size: data.len() as u64, crc32: crc32fast::hash(data.as_bytes()) });
+        let differences: Vec<_> = compare(&old, &new).into_iter()
+            .map(|difference| (difference.change, difference.name, difference.old, difference.new))
+            .collect();
+        assert_eq!(differences, [
+            (Change::Added, "added.txt".into(), None, fingerprint("added")),
+            (Change::Modified, "longer.txt".into(), fingerprint("hello"), fingerprint("hello!")),
+            (Change::Modified, "other.txt".into(), fingerprint("hello"), fingerprint("jello")),
+            (Change::Removed, "removed.txt".into(), fingerprint("removed"), None),
+        ]);
+
+        assert!(compare(&old, &old).is_empty());
+    }
+}
@@ -0,0 +1,728 @@
+use std::{ fs, io };
+use std::io::Write;
+use std::borrow::Cow;
+use std::str::FromStr;
+use std::sync::Mutex;
+use anyhow::Context;
+use camino::Utf8Path as Path;
+use serde::{ Serialize, Serializer };
+use time::{ Date, OffsetDateTime };
+use flate2::write::GzEncoder;
+use zstd::stream::write::Encoder as ZstdEncoder;
+use arrow_schema::SchemaRef;
+use crate::columnar;
+use crate::forms::Form;
+use crate::sec::{ Cik, AccessionNumber, SecData, Filing, History, Row, Timestamp };
+
+
+/// How parsed filings are written out.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Format {
+    /// one JSON object per row and line.
+    Ndjson,
+    /// comma separated values with a header row, quoted as in RFC 4180.
+    Csv,
+    /// like `Csv` but separated by tabs.
+    Tsv,
+    /// a parquet file of the filings table.
+    Parquet,
+    /// an arrow IPC stream of the filings table.
+    Arrow,
+}
+
+impl Format {
+    /// whether the format has a fixed schema of the filings table, rather than rows of text.
+    pub fn is_columnar(self) -> bool {
+        matches!(self, Format::Parquet | Format::Arrow)
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Format, String> {
+        match s {
+            "ndjson" => Ok(Format::Ndjson),
+            "csv" => Ok(Format::Csv),
+            "tsv" => Ok(Format::Tsv),
+            "parquet" => Ok(Format::Parquet),
+            "arrow" => Ok(Format::Arrow),
+            _ => Err(format!("unknown output format `{}`, expected one of: ndjson, csv, tsv, parquet, arrow", s))
+        }
+    }
+}
+
+/// What a row of the output is.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Table {
+    /// a company, with the number of its filings.
+    Companies,
+    /// a filing, with the company it belongs to.
+    Filings,
+}
+
+impl FromStr for Table {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Table, String> {
+        match s {
+            "companies" => Ok(Table::Companies),
+            "filings" => Ok(Table::Filings),
+            _ => Err(format!("unknown table `{}`, expected one of: companies, filings", s))
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// the compression an output file name asks for, `.gz` or `.zst`.
+    pub fn of_path(path: &Path) -> Option<Compression> {
+        match path.extension() {
+            Some("gz") => Some(Compression::Gzip),
+            Some("zst") => Some(Compression::Zstd),
+            _ => None
+        }
+    }
+}
+
+impl FromStr for Compression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Compression, String> {
+        match s {
+            "gzip" => Ok(Compression::Gzip),
+            "zstd" => Ok(Compression::Zstd),
+            _ => Err(format!("unknown compression `{}`, expected one of: gzip, zstd", s))
+        }
+    }
+}
+
+/// A column of the csv and tsv output, the company columns come first.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Column {
+    Cik,
+    Name,
+    EntityType,
+    Sic,
+    SicDescription,
+    Tickers,
+    Exchanges,
+    Ein,
+    Category,
+    FiscalYearEnd,
+    StateOfIncorporation,
+    Phone,
+    Website,
+    BusinessCity,
+    BusinessStateOrCountry,
+    /// the number of filings of the company, in the companies table.
+    FilingCount,
+    AccessionNumber,
+    FilingDate,
+    ReportDate,
+    AcceptanceDateTime,
+    Act,
+    Form,
+    FileNumber,
+    Items,
+    Size,
+    IsXbrl,
+    IsInlineXbrl,
+    PrimaryDocument,
+    PrimaryDocDescription,
+}
+
+impl Column {
+    const ALL: [Column; 29] = [
+        Column::Cik,
+        Column::Name,
+        Column::EntityType,
+        Column::Sic,
+        Column::SicDescription,
+        Column::Tickers,
+        Column::Exchanges,
+        Column::Ein,
+        Column::Category,
+        Column::FiscalYearEnd,
+        Column::StateOfIncorporation,
+        Column::Phone,
+        Column::Website,
+        Column::BusinessCity,
+        Column::BusinessStateOrCountry,
+        Column::FilingCount,
+        Column::AccessionNumber,
+        Column::FilingDate,
+        Column::ReportDate,
+        Column::AcceptanceDateTime,
+        Column::Act,
+        Column::Form,
+        Column::FileNumber,
+        Column::Items,
+        Column::Size,
+        Column::IsXbrl,
+        Column::IsInlineXbrl,
+        Column::PrimaryDocument,
+        Column::PrimaryDocDescription,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Column::Cik => "cik",
+            Column::Name => "name",
+            Column::EntityType => "entity_type",
+            Column::Sic => "sic",
+            Column::SicDescription => "sic_description",
+            Column::Tickers => "tickers",
+            Column::Exchanges => "exchanges",
+            Column::Ein => "ein",
+            Column::Category => "category",
+            Column::FiscalYearEnd => "fiscal_year_end",
+            Column::StateOfIncorporation => "state_of_incorporation",
+            Column::Phone => "phone",
+            Column::Website => "website",
+            Column::BusinessCity => "business_city",
+            Column::BusinessStateOrCountry => "business_state_or_country",
+            Column::FilingCount => "filing_count",
+            Column::AccessionNumber => "accession_number",
+            Column::FilingDate => "filing_date",
+            Column::ReportDate => "report_date",
+            Column::AcceptanceDateTime => "acceptance_date_time",
+            Column::Act => "act",
+            Column::Form => "form",
+            Column::FileNumber => "file_number",
+            Column::Items => "items",
+            Column::Size => "size",
+            Column::IsXbrl => "is_xbrl",
+            Column::IsInlineXbrl => "is_inline_xbrl",
+            Column::PrimaryDocument => "primary_document",
+            Column::PrimaryDocDescription => "primary_doc_description",
+        }
+    }
+
+    /// whether the column is in the table.
+    fn is_in(self, table: Table) -> bool {
+        match table {
+            Table::Companies => self < Column::AccessionNumber,
+            Table::Filings => self != Column::FilingCount,
+        }
+    }
+
+    /// the columns of a table when `--columns` isn't given, those of the ndjson output.
+    fn defaults(table: Table) -> Vec<Column> {
+        match table {
+            Table::Companies => Column::ALL.into_iter().filter(|column| column.is_in(table)).collect(),
+            Table::Filings => [Column::Cik, Column::Name, Column::Tickers, Column::Sic, Column::EntityType]
+                .into_iter()
+                .chain(Column::ALL.into_iter().filter(|&column| column >= Column::AccessionNumber))
+                .collect()
+        }
+    }
+
+    /// the value of the column, a filing column of the companies table is empty.
+    fn cell<'a>(self, company: &'a SecData, filing: Option<&'a Filing>, filing_count: usize) -> Cow<'a, str> {
+        fn text<'a>(value: Option<&'a str>) -> Cow<'a, str> {
+            Cow::Borrowed(value.unwrap_or(""))
+        }
+
+        match (self, filing) {
+            (Column::Cik, _) => company.cik.to_string().into(),
+            (Column::Name, _) => text(Some(&company.name)),
+            (Column::EntityType, _) => text(Some(company.entity_type)),
+            (Column::Sic, _) => text(Some(company.sic)),
+            (Column::SicDescription, _) => text(company.sic_description.as_deref()),
+            (Column::Tickers, _) => company.tickers.join(",").into(),
+            (Column::Exchanges, _) => company.exchanges.iter()
+                .map(|exchange| exchange.unwrap_or(""))
+                .collect::<Vec<_>>()
+                .join(",")
+                .into(),
+            (Column::Ein, _) => text(company.ein),
+            (Column::Category, _) => text(company.category.as_deref()),
+            (Column::FiscalYearEnd, _) => text(company.fiscal_year_end),
+            (Column::StateOfIncorporation, _) => text(company.state_of_incorporation),
+            (Column::Phone, _) => text(company.phone),
+            (Column::Website, _) => text(company.website.as_deref()),
+            (Column::BusinessCity, _) => text(company.addresses.business.city.as_deref()),
+            (Column::BusinessStateOrCountry, _) => text(company.addresses.business.state_or_country),
+            (Column::FilingCount, _) => filing_count.to_string().into(),
+            (_, None) => Cow::Borrowed(""),
+            (Column::AccessionNumber, Some(filing)) => filing.accession_number.to_string().into(),
+            (Column::FilingDate, Some(filing)) => filing.filing_date.to_string().into(),
+            (Column::ReportDate, Some(filing)) => filing.report_date.map(|date| date.to_string()).unwrap_or_default().into(),
+            (Column::AcceptanceDateTime, Some(filing)) => Timestamp(filing.acceptance_date_time).to_string().into(),
+            (Column::Act, Some(filing)) => text(Some(filing.act)),
+            (Column::Form, Some(filing)) => filing.form.to_string().into(),
+            (Column::FileNumber, Some(filing)) => text(Some(filing.file_number)),
+            (Column::Items, Some(filing)) => text(Some(filing.items)),
+            (Column::Size, Some(filing)) => filing.size.map(|size| size.to_string()).unwrap_or_default().into(),
+            (Column::IsXbrl, Some(filing)) => (filing.is_xbrl != 0).to_string().into(),
+            (Column::IsInlineXbrl, Some(filing)) => (filing.is_inline_xbrl != 0).to_string().into(),
+            (Column::PrimaryDocument, Some(filing)) => text(Some(filing.primary_document)),
+            (Column::PrimaryDocDescription, Some(filing)) => text(Some(filing.primary_doc_description)),
+        }
+    }
+}
+
+/// The columns given to `--columns`, separated by commas.
+#[derive(Clone, Debug)]
+pub struct Columns(pub Vec<Column>);
+
+impl FromStr for Columns {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Columns, String> {
+        s.split(',')
+            .map(|name| Column::ALL.into_iter()
+                .find(|column| column.name() == name.trim())
+                .ok_or_else(|| format!("unknown column `{}`", name)))
+            .collect::<Result<_, _>>()
+            .map(Columns)
+    }
+}
+
+/// A filing with the company it belongs to, one line of the ndjson output.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FilingRecord<'a> {
+    cik: Cik,
+    name: &'a str,
+    tickers: &'a [&'a str],
+    sic: &'a str,
+    entity_type: &'a str,
+    accession_number: AccessionNumber,
+    #[serde(serialize_with = "display")]
+    filing_date: Date,
+    #[serde(serialize_with = "display_option")]
+    report_date: Option<Date>,
+    #[serde(serialize_with = "timestamp")]
+    acceptance_date_time: OffsetDateTime,
+    act: &'a str,
+    form: &'a Form,
+    file_number: &'a str,
+    items: &'a str,
+    size: Option<i64>,
+    #[serde(rename = "isXBRL")]
+    is_xbrl: bool,
+    #[serde(rename = "isInlineXBRL")]
+    is_inline_xbrl: bool,
+    primary_document: &'a str,
+    primary_doc_description: &'a str,
+}
+
+/// A company, one line of the ndjson output of the companies table.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CompanyRecord<'a> {
+    cik: Cik,
+    name: &'a str,
+    entity_type: &'a str,
+    sic: &'a str,
+    sic_description: Option<&'a str>,
+    tickers: &'a [&'a str],
+    exchanges: &'a [Option<&'a str>],
+    ein: Option<&'a str>,
+    category: Option<&'a str>,
+    fiscal_year_end: Option<&'a str>,
+    state_of_incorporation: Option<&'a str>,
+    phone: Option<&'a str>,
+    website: Option<&'a str>,
+    business_city: Option<&'a str>,
+    business_state_or_country: Option<&'a str>,
+    filing_count: usize,
+}
+
+impl<'a> FilingRecord<'a> {
+    fn new(company: &'a SecData, filing: &'a Filing) -> FilingRecord<'a> {
+        FilingRecord {
+            cik: company.cik,
+            name: &company.name,
+            tickers: &company.tickers,
+            sic: company.sic,
+            entity_type: company.entity_type,
+            accession_number: filing.accession_number,
+            filing_date: filing.filing_date,
+            report_date: filing.report_date,
+            acceptance_date_time: filing.acceptance_date_time,
+            act: filing.act,
+            form: filing.form,
+            file_number: filing.file_number,
+            items: filing.items,
+            size: filing.size,
+            is_xbrl: filing.is_xbrl != 0,
+            is_inline_xbrl: filing.is_inline_xbrl != 0,
+            primary_document: filing.primary_document,
+            primary_doc_description: filing.primary_doc_description,
+        }
+    }
+}
+
+fn display<S: Serializer>(value: &Date, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+fn display_option<S: Serializer>(value: &Option<Date>, serializer: S) -> Result<S::Ok, S::Error> {
+    match value {
+        Some(value) => serializer.collect_str(value),
+        None => serializer.serialize_none()
+    }
+}
+
+fn timestamp<S: Serializer>(value: &OffsetDateTime, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(&Timestamp(*value))
+}
+
+/// Where the output goes: text through a stream, or columnar batches through their writer.
+enum Out {
+    Rows(Sink),
+    Columnar(columnar::Writer),
+}
+
+/// The stream the output goes through, compressed or not.
+enum Sink {
+    Plain(Box<dyn Write + Send>),
+    Gzip(GzEncoder<Box<dyn Write + Send>>),
+    Zstd(ZstdEncoder<'static, Box<dyn Write + Send>>),
+}
+
+impl Sink {
+    /// write what the encoder still holds, and flush.
+    fn finish(self) -> io::Result<()> {
+        match self {
+            Sink::Plain(mut out) => out.flush(),
+            Sink::Gzip(encoder) => encoder.finish()?.flush(),
+            Sink::Zstd(encoder) => encoder.finish()?.flush(),
+        }
+    }
+}
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Sink::Plain(out) => out.write(buf),
+            Sink::Gzip(encoder) => encoder.write(buf),
+            Sink::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Sink::Plain(out) => out.flush(),
+            Sink::Gzip(encoder) => encoder.flush(),
+            Sink::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// append a csv or tsv row, quoting cells as in RFC 4180.
+fn write_row<'a>(buf: &mut Vec<u8>, format: Format, cells: impl IntoIterator<Item = Cow<'a, str>>) {
+    let (delimiter, terminator) = match format {
+        Format::Tsv => (b'\t', &b"\n"[..]),
+        _ => (b',', &b"\r\n"[..])
+    };
+
+    for (i, cell) in cells.into_iter().enumerate() {
+        if i > 0 {
+            buf.push(delimiter);
+        }
+        if cell.bytes().any(|b| matches!(b, b'"' | b'\r' | b'\n') || b == delimiter) {
+            buf.push(b'"');
+            buf.extend_from_slice(cell.replace('"', "\"\"").as_bytes());
+            buf.push(b'"');
+        } else {
+            buf.extend_from_slice(cell.as_bytes());
+        }
+    }
+    buf.extend_from_slice(terminator);
+}
+
+/// Where the rows of every company of a run go, shared by the workers.
+///
+/// each company is serialized into a buffer of its own and written at once,
+/// so lines of parallel workers don't interleave.
+pub struct Export {
+    format: Format,
+    table: Table,
+    columns: Vec<Column>,
+    /// the schema of columnar formats.
+    schema: Option<SchemaRef>,
+    /// `None` once finished.
+    out: Mutex<Option<Out>>,
+}
+
+impl Export {
+    /// write to `path`, or stdout if `None`. csv and tsv start with the header row.
+    /// columnar formats only have the filings table and aren't compressed by a stream.
+    pub fn new(
+        format: Format,
+        table: Table,
+        columns: Option<Vec<Column>>,
+        compression: Option<Compression>,
+        path: Option<&Path>
+    ) -> anyhow::Result<Export> {
+        let columns = columns.unwrap_or_else(|| Column::defaults(table));
+        if let Some(column) = columns.iter().find(|column| !column.is_in(table)) {
+            anyhow::bail!("column `{}` isn't in the {} table", column.name(), match table {
+                Table::Companies => "companies",
+                Table::Filings => "filings",
+            });
+        }
+
+        let out: Box<dyn Write + Send> = match path {
+            Some(path) => {
+                let file = fs::File::create(path)
+                    .with_context(|| format!("create {}", path))?;
+                Box::new(io::BufWriter::new(file))
+            },
+            None => Box::new(io::stdout())
+        };
+
+        if format.is_columnar() {
+            let schema = columnar::schema();
+            let writer = match format {
+                Format::Parquet => columnar::Writer::parquet(out, schema.clone())?,
+                _ => columnar::Writer::arrow(out, &schema)?
+            };
+            return Ok(Export {
+                format,
+                table,
+                columns,
+                schema: Some(schema),
+                out: Mutex::new(Some(Out::Columnar(writer))),
+            })
+        }
+
+        let mut out = match compression.or_else(|| path.and_then(Compression::of_path)) {
+            Some(Compression::Gzip) => Sink::Gzip(GzEncoder::new(out, flate2::Compression::default())),
+            Some(Compression::Zstd) => Sink::Zstd(ZstdEncoder::new(out, 0)?),
+            None => Sink::Plain(out)
+        };
+
+        if format != Format::Ndjson {
+            let mut header = Vec::new();
+            write_row(&mut header, format, columns.iter().map(|column| Cow::Borrowed(column.name())));
+            out.write_all(&header)?;
+        }
+
+        Ok(Export { format, table, columns, schema: None, out: Mutex::new(Some(Out::Rows(out))) })
+    }
+
+    /// write a company, or its filings in `rows` of its history.
+    pub fn company(&self, company: &SecData, history: &History, rows: &[Row]) -> io::Result<()> {
+        if let Some(schema) = &self.schema {
+            if rows.is_empty() {
+                return Ok(())
+            }
+            // build the batch before taking the lock, writing it is cheap in comparison
+            let batch = columnar::batch(schema, company, history, rows).map_err(io::Error::other)?;
+            return match self.out.lock().unwrap().as_mut().expect("export already finished") {
+                Out::Columnar(writer) => writer.write(batch),
+                Out::Rows(_) => unreachable!("columnar export writes rows")
+            }
+        }
+
+        let mut buf = Vec::new();
+        match (self.table, self.format) {
+            (Table::Companies, Format::Ndjson) => {
+                let record = CompanyRecord {
+                    cik: company.cik,
+                    name: &company.name,
+                    entity_type: company.entity_type,
+                    sic: company.sic,
+                    sic_description: company.sic_description.as_deref(),
+                    tickers: &company.tickers,
+                    exchanges: &company.exchanges,
+                    ein: company.ein,
+                    category: company.category.as_deref(),
+                    fiscal_year_end: company.fiscal_year_end,
+                    state_of_incorporation: company.state_of_incorporation,
+                    phone: company.phone,
+                    website: company.website.as_deref(),
+                    business_city: company.addresses.business.city.as_deref(),
+                    business_state_or_country: company.addresses.business.state_or_country,
+                    filing_count: rows.len(),
+                };
+                serde_json::to_writer(&mut buf, &record)?;
+                buf.push(b'\n');
+            },
+            (Table::Companies, format) => {
+                let cells = self.columns.iter().map(|column| column.cell(company, None, rows.len()));
+                write_row(&mut buf, format, cells);
+            },
+            (Table::Filings, Format::Ndjson) => for &row in rows {
+                serde_json::to_writer(&mut buf, &FilingRecord::new(company, &history.filing(row)))?;
+                buf.push(b'\n');
+            },
+            (_, Format::Parquet | Format::Arrow) => unreachable!("columnar formats are written as batches"),
+            (Table::Filings, format) => for &row in rows {
+                let filing = history.filing(row);
+                let cells = self.columns.iter().map(|column| column.cell(company, Some(&filing), rows.len()));
+                write_row(&mut buf, format, cells);
+            },
+        }
+
+        if buf.is_empty() {
+            return Ok(())
+        }
+        match self.out.lock().unwrap().as_mut().expect("export already finished") {
+            Out::Rows(sink) => sink.write_all(&buf),
+            Out::Columnar(_) => unreachable!("row export writes batches")
+        }
+    }
+
+    /// finish the compressed stream and flush, once every archive is done.
+    pub fn finish(&self) -> io::Result<()> {
+        match self.out.lock().unwrap().take() {
+            Some(Out::Rows(sink)) => sink.finish(),
+            Some(Out::Columnar(writer)) => writer.finish(),
+            None => Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sec;
+    use crate::testdata;
+
+    #[test]
+    fn filing_records_carry_the_company() {
+        let mut data = testdata::COMPANY.as_bytes().to_vec();
+        let company = testdata::company(&mut data);
+        let history = sec::history(&company.filings.recent, &[]).unwrap();
+
+        let filing = history.filing(history.rows[1]);
+        let record = serde_json::to_value(FilingRecord::new(&company, &filing)).unwrap();
+        let record = record.as_object().unwrap();
+        let keys: Vec<_> = record.keys().map(String::as_str).collect();
+        assert_eq!(keys, [
+            "acceptanceDateTime",
+            "accessionNumber",
+            "act",
+            "cik",
+            "entityType",
+            "fileNumber",
+            "filingDate",
+            "form",
+            "isInlineXBRL",
+            "isXBRL",
+            "items",
+            "name",
+            "primaryDocDescription",
+            "primaryDocument",
+            "reportDate",
+            "sic",
+            "size",
+            "tickers",
+        ]);
+        // the company
+        assert_eq!(record["cik"], "0000320193");
+        assert_eq!(record["name"], "Apple Inc.");
+        assert_eq!(record["tickers"], serde_json::json!(["AAPL"]));
+        assert_eq!(record["sic"], "3571");
+        assert_eq!(record["entityType"], "operating");
+        // and the filing
+        assert_eq!(record["accessionNumber"], "0001140361-23-039575");
+        assert_eq!(record["form"], "8-K/A");
+        assert_eq!(record["reportDate"], serde_json::Value::Null);
+        assert_eq!(record["isXBRL"], false);
+    }
+
+    #[test]
+    fn ndjson_lines_stay_whole() {
+        let (_dir, dir) = testdata::temp_dir();
+        let path = dir.join("filings.ndjson");
+        let export = Export::new(Format::Ndjson, Table::Filings, None, None, Some(&path)).unwrap();
+
+        let mut data = testdata::COMPANY.as_bytes().to_vec();
+        let company = testdata::company(&mut data);
+        let history = sec::history(&company.filings.recent, &[]).unwrap();
+        // workers writing at once
+        std::thread::scope(|s| for _ in 0..4 {
+            s.spawn(|| for _ in 0..50 {
+                export.company(&company, &history, &history.rows).unwrap();
+            });
+        });
+        export.finish().unwrap();
+
+        let output = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 4 * 50 * 2);
+        for line in lines {
+            let record: serde_json::Value = serde_json::from_str(line).unwrap();
+            assert_eq!(record["cik"], "0000320193");
+        }
+    }
+
+    fn row(format: Format, cells: &[&str]) -> String {
+        let mut buf = Vec::new();
+        write_row(&mut buf, format, cells.iter().map(|&cell| Cow::Borrowed(cell)));
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn csv_quoting() {
+        assert_eq!(row(Format::Csv, &["plain", "", "tab\there"]), "plain,,tab\there\r\n");
+        assert_eq!(row(Format::Csv, &["say \"hi\""]), "\"say \"\"hi\"\"\"\r\n");
+        assert_eq!(row(Format::Csv, &["a,b", "c"]), "\"a,b\",c\r\n");
+        assert_eq!(row(Format::Csv, &["line\r\nbreak", "lf\n"]), "\"line\r\nbreak\",\"lf\n\"\r\n");
+    }
+
+    #[test]
+    fn tsv_quoting() {
+        assert_eq!(row(Format::Tsv, &["a,b", "c"]), "a,b\tc\n");
+        assert_eq!(row(Format::Tsv, &["tab\there", "x"]), "\"tab\there\"\tx\n");
+        assert_eq!(row(Format::Tsv, &["say \"hi\"", "lf\n"]), "\"say \"\"hi\"\"\"\t\"lf\n\"\n");
+    }
+
+    #[test]
+    fn columns() {
+        let Columns(columns) = "cik, form,filing_count".parse().unwrap();
+        assert_eq!(columns, [Column::Cik, Column::Form, Column::FilingCount]);
+        assert_eq!("cik,forms".parse::<Columns>().unwrap_err(), "unknown column `forms`");
+        assert_eq!("".parse::<Columns>().unwrap_err(), "unknown column ``");
+
+        assert!(Column::FilingCount.is_in(Table::Companies));
+        assert!(!Column::FilingCount.is_in(Table::Filings));
+        assert!(!Column::Form.is_in(Table::Companies));
+        let error = Export::new(Format::Csv, Table::Companies, Some(vec![Column::Cik, Column::Form]), None, None)
+            .err()
+            .unwrap();
+        assert_eq!(error.to_string(), "column `form` isn't in the companies table");
+    }
+
+    /// the csv of the test company in `table` with `columns`.
+    fn csv(table: Table, columns: &str) -> String {
+        let (_dir, dir) = testdata::temp_dir();
+        let path = dir.join("out.csv");
+        let Columns(columns) = columns.parse().unwrap();
+        let export = Export::new(Format::Csv, table, Some(columns), None, Some(&path)).unwrap();
+
+        let mut data = testdata::COMPANY.as_bytes().to_vec();
+        let company = testdata::company(&mut data);
+        let history = sec::history(&company.filings.recent, &[]).unwrap();
+        export.company(&company, &history, &history.rows).unwrap();
+        export.finish().unwrap();
+        fs::read_to_string(&path).unwrap()
+    }
+
+    #[test]
+    fn companies_and_filings_tables() {
+        assert_eq!(
+            csv(Table::Companies, "cik,name,tickers,filing_count"),
+            "cik,name,tickers,filing_count\r\n\
+             0000320193,Apple Inc.,AAPL,2\r\n"
+        );
+        assert_eq!(
+            csv(Table::Filings, "cik,accession_number,form,report_date,is_xbrl,primary_doc_description"),
+            "cik,accession_number,form,report_date,is_xbrl,primary_doc_description\r\n\
+             0000320193,0000320193-23-000106,10-K,2023-09-30,true,10-K\r\n\
+             0000320193,0001140361-23-039575,8-K/A,,false,\"FORM 8-K, \"\"amended\"\"\"\r\n"
+        );
+    }
+}
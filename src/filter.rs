@@ -0,0 +1,270 @@
+use std::fs;
+use std::str::FromStr;
+use std::collections::HashSet;
+use anyhow::Context;
+use camino::{ Utf8Path as Path, Utf8PathBuf as PathBuf };
+use regex::Regex;
+
+
+/// Named sets of patterns for archives we process regularly.
+#[derive(Clone, Copy, Debug)]
+pub enum Preset {
+    /// `CIK##########.json` company files of the SEC EDGAR `submissions.zip`.
+    SecSubmissions,
+}
+
+impl Preset {
+    fn regex(self) -> Regex {
+        match self {
+            Preset::SecSubmissions => Regex::new(r"^CIK\d{10}\.json$").unwrap(),
+        }
+    }
+}
+
+impl FromStr for Preset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Preset, String> {
+        match s {
+            "sec-submissions" => Ok(Preset::SecSubmissions),
+            _ => Err(format!("unknown preset `{}`, expected one of: sec-submissions", s))
+        }
+    }
+}
+
+/// Selects archive entries by name.
+///
+/// An entry is selected if it matches any include pattern or is listed by name,
+/// and doesn't match any exclude pattern. Without any include pattern or name,
+/// every entry is selected.
+#[derive(Default, Debug)]
+pub struct Filter {
+    include: Vec<Regex>,
+    /// the include patterns as given, they come first in `include`.
+    patterns: Vec<String>,
+    exclude: Vec<Regex>,
+    names: HashSet<String>,
+}
+
+impl Filter {
+    pub fn new(include: &[String], exclude: &[String], regex: bool) -> anyhow::Result<Filter> {
+        let compile = |pattern: &String| if regex {
+            Regex::new(pattern).with_context(|| format!("invalid regex `{}`", pattern))
+        } else {
+            glob_to_regex(pattern)
+        };
+
+        Ok(Filter {
+            include: include.iter().map(compile).collect::<anyhow::Result<_>>()?,
+            patterns: include.to_vec(),
+            exclude: exclude.iter().map(compile).collect::<anyhow::Result<_>>()?,
+            names: HashSet::new(),
+        })
+    }
+
+    pub fn preset(&mut self, preset: Preset) {
+        self.include.push(preset.regex());
+    }
+
+    /// read entry names from a file, one per line.
+    pub fn names_from(&mut self, path: &Path) -> anyhow::Result<()> {
+        let list = fs::read_to_string(path)
+            .with_context(|| format!("couldn't read names from {}", path))?;
+        let names = list.lines()
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(String::from);
+        self.add_names(names);
+        Ok(())
+    }
+
+    /// select entries by their exact name.
+    pub fn add_names(&mut self, names: impl IntoIterator<Item = String>) {
+        self.names.extend(names);
+    }
+
+    pub fn is_match(&self, name: &str) -> bool {
+        let included = (self.include.is_empty() && self.names.is_empty())
+            || self.names.contains(name)
+            || self.include.iter().any(|re| re.is_match(name));
+
+        included && !self.exclude.iter().any(|re| re.is_match(name))
+    }
+
+    /// the include patterns given on the command line that match none of `names`.
+    pub fn unmatched<S: AsRef<str>>(&self, names: impl IntoIterator<Item = S>) -> Vec<&str> {
+        let mut matched = vec![false; self.patterns.len()];
+        let mut left = matched.len();
+
+        for name in names {
+            if left == 0 {
+                break
+            }
+            let patterns = self.include[..matched.len()].iter().zip(matched.iter_mut());
+            for (re, matched) in patterns.filter(|(_, matched)| !**matched) {
+                if re.is_match(name.as_ref()) {
+                    *matched = true;
+                    left -= 1;
+                }
+            }
+        }
+
+        self.patterns.iter()
+            .zip(matched)
+            .filter(|(_, matched)| !matched)
+            .map(|(pattern, _)| pattern.as_str())
+            .collect()
+    }
+}
+
+/// translate an unzip-style wildcard into an anchored regex.
+///
+/// `*` matches any sequence of characters (including `/`), `?` matches a single
+/// character, `[...]` matches a character class (`[!...]` or `[^...]` negated)
+/// and `\` escapes the next character.
+pub fn glob_to_regex(pattern: &str) -> anyhow::Result<Regex> {
+    let mut re = String::with_capacity(pattern.len() + 8);
+    let mut chars = pattern.chars();
+
+    re.push('^');
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => re.push_str(".*"),
+            '?' => re.push('.'),
+            '[' => {
+                let mut first = true;
+                let mut closed = false;
+                re.push('[');
+                for c in chars.by_ref() {
+                    match c {
+                        ']' if !first => {
+                            closed = true;
+                            break
+                        },
+                        '!' | '^' if first => {
+                            re.push('^');
+                            continue
+                        },
+                        '-' => re.push('-'),
+                        c => re.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+                    }
+                    first = false;
+                }
+                re.push(']');
+                anyhow::ensure!(closed, "unclosed `[` in pattern `{}`", pattern);
+            },
+            '\\' => if let Some(c) = chars.next() {
+                re.push_str(&regex::escape(c.encode_utf8(&mut [0; 4])));
+            },
+            c => re.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    re.push('$');
+
+    Regex::new(&re).with_context(|| format!("invalid pattern `{}`", pattern))
+}
+
+fn has_wildcard(s: &str) -> bool {
+    s.contains(['*', '?', '['])
+}
+
+/// expand a wildcard in the file name of an archive path, like unzip does.
+///
+/// Paths without wildcards are returned unchanged, even if they don't exist.
+pub fn glob_archives(spec: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let file_name = match spec.file_name() {
+        Some(name) if has_wildcard(name) => name,
+        _ => return Ok(vec![spec.to_path_buf()])
+    };
+    let dir = match spec.parent() {
+        Some(dir) if !dir.as_str().is_empty() => dir,
+        _ => Path::new(".")
+    };
+    let re = glob_to_regex(file_name)?;
+
+    let mut archives = Vec::new();
+    for entry in dir.read_dir_utf8().with_context(|| format!("couldn't read {}", dir))? {
+        let entry = entry?;
+        if entry.file_type()?.is_file() && re.is_match(entry.file_name()) {
+            archives.push(spec.with_file_name(entry.file_name()));
+        }
+    }
+    archives.sort();
+
+    if archives.is_empty() {
+        archives.push(spec.to_path_buf());
+    }
+
+    Ok(archives)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, name: &str) -> bool {
+        glob_to_regex(pattern).unwrap().is_match(name)
+    }
+
+    #[test]
+    fn wildcards() {
+        assert!(matches("*.json", "a/CIK0000320193.json"));
+        assert!(!matches("*.json", "a.json.bak"));
+        assert!(matches("CIK??.json", "CIK12.json"));
+        assert!(!matches("CIK??.json", "CIK1.json"));
+        // other regex syntax is literal
+        assert!(matches("a+b(1).txt", "a+b(1).txt"));
+        assert!(!matches("a.b", "axb"));
+    }
+
+    #[test]
+    fn classes() {
+        assert!(matches("[a-c]x", "bx"));
+        assert!(!matches("[a-c]x", "dx"));
+        assert!(matches("[!a-c]x", "dx"));
+        assert!(!matches("[!a-c]x", "ax"));
+        assert!(matches("[^a]x", "bx"));
+    }
+
+    #[test]
+    fn bracket_first_in_class_is_literal() {
+        assert!(matches("[]a]", "]"));
+        assert!(matches("[]a]", "a"));
+        assert!(!matches("[]a]", "b"));
+        assert!(matches("[!]a]", "b"));
+        assert!(!matches("[!]a]", "]"));
+    }
+
+    #[test]
+    fn backslash_escapes() {
+        assert!(matches(r"a\*", "a*"));
+        assert!(!matches(r"a\*", "ab"));
+        assert!(matches(r"\[x]", "[x]"));
+        assert!(matches(r"a\\b", r"a\b"));
+    }
+
+    #[test]
+    fn unclosed_bracket_is_an_error() {
+        assert!(glob_to_regex("[abc").is_err());
+        assert!(glob_to_regex("[]").is_err());
+        assert!(glob_to_regex("[!").is_err());
+    }
+
+    #[test]
+    fn filter_selects_and_excludes() {
+        let filter = Filter::new(&["*.json".into()], &["CIK1*".into()], false).unwrap();
+        assert!(filter.is_match("CIK2.json"));
+        assert!(!filter.is_match("CIK1.json"));
+        assert!(!filter.is_match("CIK2.txt"));
+        assert!(Filter::default().is_match("anything"));
+    }
+
+    #[test]
+    fn unmatched_patterns() {
+        let mut filter = Filter::new(&["*.json".into(), "new.zip".into()], &[], false).unwrap();
+        filter.preset(Preset::SecSubmissions);
+        assert_eq!(filter.unmatched(["a.json", "b.txt"]), ["new.zip"]);
+        assert_eq!(filter.unmatched(["new.zip", "a.json"]), Vec::<&str>::new());
+        assert!(Filter::default().unmatched(["a"]).is_empty());
+    }
+}
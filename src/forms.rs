@@ -0,0 +1,494 @@
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::collections::BTreeMap;
+use camino::Utf8Path as Path;
+use serde::{ de, Deserialize, Deserializer, Serialize, Serializer };
+use crate::sec::InvalidValue;
+
+
+/// EDGAR form type of a filing, without the `/A` of amendments.
+///
+/// the forms analysts ask for by name, anything else is kept as `Other`.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum FormType {
+    Form10K,
+    Form10KT,
+    Form10Q,
+    Form10QT,
+    Form20F,
+    Form40F,
+    Form11K,
+    Form8K,
+    Form6K,
+    S1,
+    S3,
+    S4,
+    S8,
+    S11,
+    F1,
+    F3,
+    F4,
+    /// `424B1` to `424B8`, the prospectus filed under the rule 424(b) paragraph.
+    Form424B(Rule424B),
+    Fwp,
+    PosAm,
+    /// notice of an exempt offering.
+    FormD,
+    Form3,
+    Form4,
+    Form5,
+    Form144,
+    Form13FHr,
+    Form13FNt,
+    /// `SC 13D`, or `SCHEDULE 13D` as it's named since 2024, which `schedule` tells.
+    Sc13D { schedule: bool },
+    /// `SC 13G`, or `SCHEDULE 13G` as it's named since 2024, which `schedule` tells.
+    Sc13G { schedule: bool },
+    Def14A,
+    DefA14A,
+    DefM14A,
+    Pre14A,
+    NPortP,
+    NCsr,
+    NCen,
+    Form485BPos,
+    Form497,
+    Other(String),
+}
+
+/// A paragraph of rule 424(b), `1` to `8`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Rule424B(u8);
+
+impl Rule424B {
+    const FORMS: [&'static str; 8] = ["424B1", "424B2", "424B3", "424B4", "424B5", "424B6", "424B7", "424B8"];
+
+    pub fn new(paragraph: u8) -> Option<Rule424B> {
+        (1..=8).contains(&paragraph).then_some(Rule424B(paragraph))
+    }
+
+    /// the form a prospectus filed under the paragraph is named after.
+    pub fn form(self) -> &'static str {
+        Rule424B::FORMS[usize::from(self.0 - 1)]
+    }
+}
+
+/// Groups of form types that are usually asked for together.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum FormFamily {
+    /// annual and quarterly reports.
+    Periodic,
+    /// reports of events in between.
+    Current,
+    /// offerings of securities, registered or exempt.
+    Registration,
+    /// holdings of insiders, large holders and institutional managers.
+    Ownership,
+    /// solicitations for shareholder votes.
+    Proxy,
+    /// reports and prospectuses of investment companies.
+    Fund,
+}
+
+impl FromStr for FormFamily {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<FormFamily, String> {
+        match s {
+            "periodic" => Ok(FormFamily::Periodic),
+            "current" => Ok(FormFamily::Current),
+            "registration" => Ok(FormFamily::Registration),
+            "ownership" => Ok(FormFamily::Ownership),
+            "proxy" => Ok(FormFamily::Proxy),
+            "fund" => Ok(FormFamily::Fund),
+            _ => Err(format!("unknown form family `{}`, expected one of: periodic, current, registration, ownership, proxy, fund", s))
+        }
+    }
+}
+
+impl FormType {
+    /// the form type of an EDGAR form name without the `/A`, `as_str` gives the name back.
+    pub fn parse(s: &str) -> FormType {
+        match s {
+            "10-K" => FormType::Form10K,
+            "10-KT" => FormType::Form10KT,
+            "10-Q" => FormType::Form10Q,
+            "10-QT" => FormType::Form10QT,
+            "20-F" => FormType::Form20F,
+            "40-F" => FormType::Form40F,
+            "11-K" => FormType::Form11K,
+            "8-K" => FormType::Form8K,
+            "6-K" => FormType::Form6K,
+            "S-1" => FormType::S1,
+            "S-3" => FormType::S3,
+            "S-4" => FormType::S4,
+            "S-8" => FormType::S8,
+            "S-11" => FormType::S11,
+            "F-1" => FormType::F1,
+            "F-3" => FormType::F3,
+            "F-4" => FormType::F4,
+            "FWP" => FormType::Fwp,
+            "POS AM" => FormType::PosAm,
+            "D" => FormType::FormD,
+            "3" => FormType::Form3,
+            "4" => FormType::Form4,
+            "5" => FormType::Form5,
+            "144" => FormType::Form144,
+            "13F-HR" => FormType::Form13FHr,
+            "13F-NT" => FormType::Form13FNt,
+            "SC 13D" => FormType::Sc13D { schedule: false },
+            "SCHEDULE 13D" => FormType::Sc13D { schedule: true },
+            "SC 13G" => FormType::Sc13G { schedule: false },
+            "SCHEDULE 13G" => FormType::Sc13G { schedule: true },
+            "DEF 14A" => FormType::Def14A,
+            "DEFA14A" => FormType::DefA14A,
+            "DEFM14A" => FormType::DefM14A,
+            "PRE 14A" => FormType::Pre14A,
+            "NPORT-P" => FormType::NPortP,
+            "N-CSR" => FormType::NCsr,
+            "N-CEN" => FormType::NCen,
+            "485BPOS" => FormType::Form485BPos,
+            "497" => FormType::Form497,
+            _ => match s.strip_prefix("424B").filter(|n| n.len() == 1).and_then(|n| n.parse().ok()).and_then(Rule424B::new) {
+                Some(paragraph) => FormType::Form424B(paragraph),
+                None => FormType::Other(s.into())
+            }
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            FormType::Form10K => "10-K",
+            FormType::Form10KT => "10-KT",
+            FormType::Form10Q => "10-Q",
+            FormType::Form10QT => "10-QT",
+            FormType::Form20F => "20-F",
+            FormType::Form40F => "40-F",
+            FormType::Form11K => "11-K",
+            FormType::Form8K => "8-K",
+            FormType::Form6K => "6-K",
+            FormType::S1 => "S-1",
+            FormType::S3 => "S-3",
+            FormType::S4 => "S-4",
+            FormType::S8 => "S-8",
+            FormType::S11 => "S-11",
+            FormType::F1 => "F-1",
+            FormType::F3 => "F-3",
+            FormType::F4 => "F-4",
+            FormType::Form424B(paragraph) => paragraph.form(),
+            FormType::Fwp => "FWP",
+            FormType::PosAm => "POS AM",
+            FormType::FormD => "D",
+            FormType::Form3 => "3",
+            FormType::Form4 => "4",
+            FormType::Form5 => "5",
+            FormType::Form144 => "144",
+            FormType::Form13FHr => "13F-HR",
+            FormType::Form13FNt => "13F-NT",
+            FormType::Sc13D { schedule: false } => "SC 13D",
+            FormType::Sc13D { schedule: true } => "SCHEDULE 13D",
+            FormType::Sc13G { schedule: false } => "SC 13G",
+            FormType::Sc13G { schedule: true } => "SCHEDULE 13G",
+            FormType::Def14A => "DEF 14A",
+            FormType::DefA14A => "DEFA14A",
+            FormType::DefM14A => "DEFM14A",
+            FormType::Pre14A => "PRE 14A",
+            FormType::NPortP => "NPORT-P",
+            FormType::NCsr => "N-CSR",
+            FormType::NCen => "N-CEN",
+            FormType::Form485BPos => "485BPOS",
+            FormType::Form497 => "497",
+            FormType::Other(name) => name,
+        }
+    }
+
+    /// the group the form type belongs to, `None` for `Other`.
+    pub fn family(&self) -> Option<FormFamily> {
+        use FormType::*;
+
+        Some(match self {
+            Form10K | Form10KT | Form10Q | Form10QT | Form20F | Form40F | Form11K => FormFamily::Periodic,
+            Form8K | Form6K => FormFamily::Current,
+            S1 | S3 | S4 | S8 | S11 | F1 | F3 | F4 | Form424B(_) | Fwp | PosAm | FormD => FormFamily::Registration,
+            Form3 | Form4 | Form5 | Form144 | Form13FHr | Form13FNt | Sc13D { .. } | Sc13G { .. } => FormFamily::Ownership,
+            Def14A | DefA14A | DefM14A | Pre14A => FormFamily::Proxy,
+            NPortP | NCsr | NCen | Form485BPos | Form497 => FormFamily::Fund,
+            Other(_) => return None
+        })
+    }
+
+    /// the annual reports of domestic, transition period and foreign filers.
+    pub fn is_annual_report(&self) -> bool {
+        matches!(self, FormType::Form10K | FormType::Form10KT | FormType::Form20F | FormType::Form40F)
+    }
+}
+
+impl fmt::Display for FormType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The `form` of a filing: its type and whether it amends an earlier filing, `10-K/A`.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Form {
+    pub form_type: FormType,
+    pub amendment: bool,
+}
+
+impl Form {
+    pub fn parse(s: &str) -> Form {
+        match s.strip_suffix("/A") {
+            Some(base) => Form { form_type: FormType::parse(base), amendment: true },
+            None => Form { form_type: FormType::parse(s), amendment: false }
+        }
+    }
+}
+
+impl fmt::Display for Form {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.form_type.as_str())?;
+        if self.amendment {
+            f.write_str("/A")?;
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for Form {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Form {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Form, D::Error> {
+        struct FormVisitor;
+
+        impl de::Visitor<'_> for FormVisitor {
+            type Value = Form;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("an EDGAR form type like 10-K or 8-K/A")
+            }
+
+            fn visit_str<E: de::Error>(self, s: &str) -> Result<Form, E> {
+                Ok(Form::parse(s))
+            }
+        }
+
+        deserializer.deserialize_str(FormVisitor)
+    }
+}
+
+/// Items of Form 8-K with their official titles, the numbering since August 2004
+/// first and the one before after it.
+const ITEMS: &[(&str, &str)] = &[
+    ("1.01", "Entry into a Material Definitive Agreement"),
+    ("1.02", "Termination of a Material Definitive Agreement"),
+    ("1.03", "Bankruptcy or Receivership"),
+    ("1.04", "Mine Safety - Reporting of Shutdowns and Patterns of Violations"),
+    ("1.05", "Material Cybersecurity Incidents"),
+    ("2.01", "Completion of Acquisition or Disposition of Assets"),
+    ("2.02", "Results of Operations and Financial Condition"),
+    ("2.03", "Creation of a Direct Financial Obligation or an Obligation under an Off-Balance Sheet Arrangement of a Registrant"),
+    ("2.04", "Triggering Events That Accelerate or Increase a Direct Financial Obligation or an Obligation under an Off-Balance Sheet Arrangement"),
+    ("2.05", "Costs Associated with Exit or Disposal Activities"),
+    ("2.06", "Material Impairments"),
+    ("3.01", "Notice of Delisting or Failure to Satisfy a Continued Listing Rule or Standard; Transfer of Listing"),
+    ("3.02", "Unregistered Sales of Equity Securities"),
+    ("3.03", "Material Modification to Rights of Security Holders"),
+    ("4.01", "Changes in Registrant's Certifying Accountant"),
+    ("4.02", "Non-Reliance on Previously Issued Financial Statements or a Related Audit Report or Completed Interim Review"),
+    ("5.01", "Changes in Control of Registrant"),
+    ("5.02", "Departure of Directors or Certain Officers; Election of Directors; Appointment of Certain Officers; Compensatory Arrangements of Certain Officers"),
+    ("5.03", "Amendments to Articles of Incorporation or Bylaws; Change in Fiscal Year"),
+    ("5.04", "Temporary Suspension of Trading Under Registrant's Employee Benefit Plans"),
+    ("5.05", "Amendments to the Registrant's Code of Ethics, or Waiver of a Provision of the Code of Ethics"),
+    ("5.06", "Change in Shell Company Status"),
+    ("5.07", "Submission of Matters to a Vote of Security Holders"),
+    ("5.08", "Shareholder Director Nominations"),
+    ("6.01", "ABS Informational and Computational Material"),
+    ("6.02", "Change of Servicer or Trustee"),
+    ("6.03", "Change in Credit Enhancement or Other External Support"),
+    ("6.04", "Failure to Make a Required Distribution"),
+    ("6.05", "Securities Act Updating Disclosure"),
+    ("6.06", "Static Pool"),
+    ("6.10", "Alternative Filings of Asset-Backed Issuers"),
+    ("7.01", "Regulation FD Disclosure"),
+    ("8.01", "Other Events"),
+    ("9.01", "Financial Statements and Exhibits"),
+    ("1", "Changes in Control of Registrant"),
+    ("2", "Acquisition or Disposition of Assets"),
+    ("3", "Bankruptcy or Receivership"),
+    ("4", "Changes in Registrant's Certifying Accountant"),
+    ("5", "Other Events"),
+    ("6", "Resignations of Registrant's Directors"),
+    ("7", "Financial Statements and Exhibits"),
+    ("8", "Change in Fiscal Year"),
+    ("9", "Regulation FD Disclosure"),
+    ("10", "Amendments to the Registrant's Code of Ethics, or Waiver of a Provision of the Code of Ethics"),
+    ("11", "Temporary Suspension of Trading Under Registrant's Employee Benefit Plans"),
+    ("12", "Results of Operations and Financial Condition"),
+];
+
+/// An item reported by a Form 8-K, `2.02`, or `5` in the numbering before August 2004.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct ItemCode(u8);
+
+impl ItemCode {
+    pub fn code(self) -> &'static str {
+        ITEMS[usize::from(self.0)].0
+    }
+
+    #[allow(dead_code)]
+    pub fn description(self) -> &'static str {
+        ITEMS[usize::from(self.0)].1
+    }
+
+    /// whether the item is numbered the way it was before August 2004.
+    #[allow(dead_code)]
+    pub fn is_legacy(self) -> bool {
+        !self.code().contains('.')
+    }
+
+    /// the items of an `items` column, like `2.02,9.01`.
+    pub fn parse_list(s: &str) -> Result<Vec<ItemCode>, InvalidValue> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|code| !code.is_empty())
+            .map(str::parse)
+            .collect()
+    }
+}
+
+impl FromStr for ItemCode {
+    type Err = InvalidValue;
+
+    fn from_str(s: &str) -> Result<ItemCode, InvalidValue> {
+        ITEMS.iter()
+            .position(|&(code, _)| code == s)
+            .map(|i| ItemCode(i as u8))
+            .ok_or_else(|| InvalidValue::new("8-K item", s))
+    }
+}
+
+impl fmt::Display for ItemCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+/// The unknown 8-K item codes of the filings of an archive, with the number of filings of each.
+#[derive(Default)]
+pub struct UnknownItems {
+    counts: Mutex<BTreeMap<String, usize>>,
+}
+
+impl UnknownItems {
+    pub fn add(&self, code: &str) {
+        *self.counts.lock().unwrap().entry(code.into()).or_default() += 1;
+    }
+
+    /// one line for all of them, `None` if there were none.
+    fn summary(&self) -> Option<String> {
+        let counts = self.counts.lock().unwrap();
+        if counts.is_empty() {
+            return None
+        }
+
+        let codes: Vec<String> = counts.iter()
+            .map(|(code, count)| format!("`{}` ({})", code, count))
+            .collect();
+        Some(format!("{} 8-K filings with unknown items not matched: {}",
+            counts.values().sum::<usize>(),
+            codes.join(", ")
+        ))
+    }
+
+    pub fn eprint(&self, path: &Path) {
+        if let Some(summary) = self.summary() {
+            eprintln!("unzipx: warning: {}: {}", path, summary);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn forms_keep_their_spelling() {
+        for name in [
+            "10-K", "10-K/A", "8-K", "424B1", "424B8", "SC 13D", "SC 13D/A", "SCHEDULE 13D", "SCHEDULE 13D/A",
+            "SC 13G", "SCHEDULE 13G/A", "DEF 14A", "NPORT-P", "D/A", "424B9", "424B01", "424B+1", "ARS", "",
+        ] {
+            assert_eq!(Form::parse(name).to_string(), name);
+        }
+    }
+
+    #[test]
+    fn form_types() {
+        assert_eq!(Form::parse("10-K/A"), Form { form_type: FormType::Form10K, amendment: true });
+        assert_eq!(Form::parse("424B3").form_type, FormType::Form424B(Rule424B::new(3).unwrap()));
+        assert_eq!(Form::parse("424B9").form_type, FormType::Other("424B9".into()));
+        assert_eq!(Form::parse("SCHEDULE 13D/A").form_type, FormType::Sc13D { schedule: true });
+        assert_eq!(Form::parse("SC 13G").form_type.family(), Some(FormFamily::Ownership));
+        assert_eq!(Form::parse("SCHEDULE 13G").form_type.family(), Some(FormFamily::Ownership));
+        assert_eq!(Form::parse("ARS").form_type.family(), None);
+    }
+
+    #[test]
+    fn rule_424b_paragraphs() {
+        assert_eq!(Rule424B::new(0), None);
+        assert_eq!(Rule424B::new(9), None);
+        for n in 1..=8 {
+            let form = Rule424B::new(n).unwrap().form();
+            assert_eq!(form, format!("424B{}", n));
+            assert_eq!(FormType::parse(form), FormType::Form424B(Rule424B::new(n).unwrap()));
+        }
+    }
+
+    #[test]
+    fn annual_reports() {
+        for name in ["10-K", "10-K/A", "10-KT", "20-F", "40-F/A"] {
+            assert!(Form::parse(name).form_type.is_annual_report(), "{}", name);
+        }
+        for name in ["10-Q", "8-K", "ARS", "11-K"] {
+            assert!(!Form::parse(name).form_type.is_annual_report(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn item_codes() {
+        let item: ItemCode = "5.02".parse().unwrap();
+        assert_eq!(item.to_string(), "5.02");
+        assert!(item.description().starts_with("Departure of Directors"));
+        assert!(!item.is_legacy());
+        assert!("12".parse::<ItemCode>().unwrap().is_legacy());
+        for s in ["", "5.2", "5.020", "Item 2.02", "13", "9.02"] {
+            assert!(s.parse::<ItemCode>().is_err(), "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn unknown_items_are_summed_up() {
+        let unknown = UnknownItems::default();
+        assert_eq!(unknown.summary(), None);
+
+        for code in ["9.99", "1.10", "9.99", "Item 2"] {
+            unknown.add(code);
+        }
+        assert_eq!(unknown.summary().unwrap(), "4 8-K filings with unknown items not matched: `1.10` (1), `9.99` (2), `Item 2` (1)");
+    }
+
+    #[test]
+    fn item_lists() {
+        let codes = |s| ItemCode::parse_list(s).unwrap().iter().map(ItemCode::to_string).collect::<Vec<_>>();
+        assert_eq!(codes("2.02,9.01"), ["2.02", "9.01"]);
+        assert_eq!(codes(" 2.02 , 9.01 ,"), ["2.02", "9.01"]);
+        assert!(codes("").is_empty());
+        assert!(ItemCode::parse_list("2.02,Item 9.01").is_err());
+    }
+}
@@ -0,0 +1,263 @@
+use std::str::FromStr;
+use std::sync::{ Mutex, Condvar };
+use std::sync::atomic::{ AtomicU64, Ordering };
+use zip_parser::CentralFileHeader;
+use crate::report::{ Kind, Failure };
+
+
+/// A size in bytes, parsed from a number with an optional `K`, `M`, `G` or `T` suffix (powers of 1024).
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct ByteSize(pub u64);
+
+impl FromStr for ByteSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ByteSize, String> {
+        let s = s.trim();
+        let digits = s.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+        let shift = match s[digits.len()..].to_ascii_uppercase().as_str() {
+            "" | "B" => 0,
+            "K" | "KB" | "KIB" => 10,
+            "M" | "MB" | "MIB" => 20,
+            "G" | "GB" | "GIB" => 30,
+            "T" | "TB" | "TIB" => 40,
+            suffix => return Err(format!("unknown size suffix `{}`", suffix))
+        };
+        let n: u64 = digits.parse()
+            .map_err(|err| format!("invalid size `{}`: {}", s, err))?;
+
+        n.checked_mul(1 << shift)
+            .map(ByteSize)
+            .ok_or_else(|| format!("size `{}` is too large", s))
+    }
+}
+
+/// Bounds on what a run may decompress.
+#[derive(Clone, Copy, Debug)]
+pub struct Limits {
+    /// entries selected across all archives.
+    pub max_entries: u64,
+    /// uncompressed bytes across all archives.
+    pub max_total_size: ByteSize,
+    /// uncompressed bytes of a single entry.
+    pub max_entry_size: ByteSize,
+    /// uncompressed size divided by compressed size of a single entry.
+    pub max_ratio: u64,
+    /// bytes of decoded data held in memory at once.
+    pub max_memory: ByteSize,
+}
+
+impl Default for Limits {
+    fn default() -> Limits {
+        Limits {
+            max_entries: 10_000_000,
+            max_total_size: ByteSize(1 << 40),
+            max_entry_size: ByteSize(4 << 30),
+            max_ratio: 1000,
+            max_memory: ByteSize(1 << 30),
+        }
+    }
+}
+
+/// Limits shared by every worker of a run.
+///
+/// Entries are admitted by their declared sizes before anything is decompressed,
+/// the readers are bound to the declared uncompressed size so it can't be exceeded.
+pub struct Budget {
+    limits: Limits,
+    entries: AtomicU64,
+    total_size: AtomicU64,
+}
+
+impl Budget {
+    pub fn new(limits: Limits) -> Budget {
+        Budget {
+            limits,
+            entries: AtomicU64::new(0),
+            total_size: AtomicU64::new(0),
+        }
+    }
+
+    pub fn admit(&self, cfh: &CentralFileHeader, name: &str) -> Result<(), Failure> {
+        let comp_size = u64::from(cfh.comp_size);
+        let uncomp_size = u64::from(cfh.uncomp_size);
+
+        if uncomp_size > self.limits.max_entry_size.0 {
+            return Err(Failure::new(name, Kind::EntryTooLarge, format_args!(
+                "uncompressed size {} exceeds --max-entry-size {}",
+                uncomp_size,
+                self.limits.max_entry_size.0
+            )))
+        }
+
+        let ratio = uncomp_size / comp_size.max(1);
+        if ratio > self.limits.max_ratio {
+            return Err(Failure::new(name, Kind::Ratio, format_args!(
+                "compression ratio {} exceeds --max-ratio {}",
+                ratio,
+                self.limits.max_ratio
+            )))
+        }
+
+        let entries = self.entries.fetch_add(1, Ordering::Relaxed) + 1;
+        if entries > self.limits.max_entries {
+            // only admitted entries count
+            self.entries.fetch_sub(1, Ordering::Relaxed);
+            return Err(Failure::new(name, Kind::TooManyEntries, format_args!(
+                "more than --max-entries {} entries",
+                self.limits.max_entries
+            )))
+        }
+
+        let total_size = self.total_size.fetch_add(uncomp_size, Ordering::Relaxed) + uncomp_size;
+        if total_size > self.limits.max_total_size.0 {
+            // give the reservation back, smaller entries may still fit
+            self.total_size.fetch_sub(uncomp_size, Ordering::Relaxed);
+            self.entries.fetch_sub(1, Ordering::Relaxed);
+            return Err(Failure::new(name, Kind::TotalTooLarge, format_args!(
+                "total uncompressed size exceeds --max-total-size {}",
+                self.limits.max_total_size.0
+            )))
+        }
+
+        Ok(())
+    }
+}
+
+/// Bytes of decoded data in memory, shared by every worker of a run.
+///
+/// Workers reserve what they are about to hold before decoding and wait while the budget is used up.
+/// A reservation larger than the whole budget waits until it's the only one.
+pub struct MemoryBudget {
+    capacity: u64,
+    in_flight: Mutex<u64>,
+    freed: Condvar,
+}
+
+/// Memory reserved from a `MemoryBudget`, given back on drop.
+pub struct Reservation<'a> {
+    budget: &'a MemoryBudget,
+    size: u64,
+}
+
+impl MemoryBudget {
+    pub fn new(capacity: ByteSize) -> MemoryBudget {
+        MemoryBudget {
+            capacity: capacity.0.max(1),
+            in_flight: Mutex::new(0),
+            freed: Condvar::new(),
+        }
+    }
+
+    /// block until `size` bytes are free.
+    pub fn acquire(&self, size: u64) -> Reservation<'_> {
+        let size = size.min(self.capacity);
+        let in_flight = self.in_flight.lock().unwrap();
+        let mut in_flight = self.freed
+            .wait_while(in_flight, |in_flight| *in_flight + size > self.capacity)
+            .unwrap();
+        *in_flight += size;
+        Reservation { budget: self, size }
+    }
+
+    /// reserve `size` bytes if they are free right now.
+    pub fn try_acquire(&self, size: u64) -> Option<Reservation<'_>> {
+        let size = size.min(self.capacity);
+        let mut in_flight = self.in_flight.lock().unwrap();
+        if *in_flight + size > self.capacity {
+            return None
+        }
+        *in_flight += size;
+        Some(Reservation { budget: self, size })
+    }
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        *self.budget.in_flight.lock().unwrap() -= self.size;
+        self.budget.freed.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zip_parser::{ compress, Zip64Archive };
+    use crate::testdata::{ self, Entry };
+
+    #[test]
+    fn byte_size_suffixes() {
+        assert_eq!("123".parse(), Ok(ByteSize(123)));
+        assert_eq!("123B".parse(), Ok(ByteSize(123)));
+        assert_eq!("4k".parse(), Ok(ByteSize(4 << 10)));
+        assert_eq!("4KiB".parse(), Ok(ByteSize(4 << 10)));
+        assert_eq!("64M".parse(), Ok(ByteSize(64 << 20)));
+        assert_eq!(" 4G ".parse(), Ok(ByteSize(4 << 30)));
+        assert_eq!("1TB".parse(), Ok(ByteSize(1 << 40)));
+    }
+
+    #[test]
+    fn byte_size_errors() {
+        for s in ["", "G", "1.5G", "-1", "4P", "4 G"] {
+            assert!(s.parse::<ByteSize>().is_err(), "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn byte_size_overflow() {
+        assert_eq!("16777215T".parse(), Ok(ByteSize(16777215 << 40)));
+        assert!("16777216T".parse::<ByteSize>().is_err());
+        assert_eq!("18446744073709551615".parse(), Ok(ByteSize(u64::MAX)));
+        assert!("18446744073709551616".parse::<ByteSize>().is_err());
+    }
+
+    #[test]
+    fn memory_budget() {
+        let memory = MemoryBudget::new(ByteSize(100));
+        let a = memory.acquire(60);
+        assert!(memory.try_acquire(50).is_none());
+        let b = memory.try_acquire(40).unwrap();
+        drop(a);
+        // larger than the whole budget, it waits until it's alone
+        assert!(memory.try_acquire(1000).is_none());
+        drop(b);
+        assert!(memory.try_acquire(1000).is_some());
+    }
+
+    #[test]
+    fn rejected_entries_dont_count() {
+        let stored = |name, size| Entry::new(name, vec![b'x'; size]).method(compress::STORE);
+        let buf = testdata::zip(&[
+            stored("big", 80),
+            stored("too big", 90),
+            stored("total", 30),
+            Entry::new("bomb", vec![0; 80]),
+            stored("a", 10),
+            stored("b", 10),
+            stored("c", 1),
+        ]);
+        let zip = Zip64Archive::parse(&buf).unwrap();
+        let budget = Budget::new(Limits {
+            max_entries: 3,
+            max_total_size: ByteSize(100),
+            max_entry_size: ByteSize(85),
+            max_ratio: 2,
+            ..Limits::default()
+        });
+
+        let kinds: Vec<_> = zip.entries().unwrap()
+            .map(|cfh| budget.admit(&cfh.unwrap(), "entry").err().map(|failure| failure.kind))
+            .collect();
+        assert_eq!(kinds, [
+            None,
+            Some(Kind::EntryTooLarge),
+            Some(Kind::TotalTooLarge),
+            Some(Kind::Ratio),
+            None,
+            None,
+            Some(Kind::TooManyEntries),
+        ]);
+        assert_eq!(budget.entries.load(Ordering::Relaxed), 3);
+        assert_eq!(budget.total_size.load(Ordering::Relaxed), 100);
+    }
+}
@@ -1,4 +1,19 @@
 mod util;
+mod report;
+mod filter;
+mod progress;
+mod limits;
+mod sanitize;
+mod charset;
+mod order;
+mod manifest;
+mod diff;
+mod sec;
+mod forms;
+mod export;
+mod columnar;
+#[cfg(test)]
+mod testdata;
 
 #[cfg(not(target_env = "msvc"))]
 use jemallocator::Jemalloc;
@@ -6,129 +21,350 @@ use jemallocator::Jemalloc;
 #[global_allocator]
 static GLOBAL: Jemalloc = Jemalloc;
 
-use std::{ env, fs };
-use std::io::Read;
+use std::{ env, fs, io };
+use std::sync::Mutex;
+use std::sync::atomic::{ AtomicBool, AtomicUsize, Ordering };
+use std::io::{ Read, Write };
 use std::borrow::Cow;
+use std::collections::HashMap;
+use std::process::ExitCode;
 use anyhow::Context;
 use camino::{ Utf8Path as Path, Utf8PathBuf as PathBuf };
 use argh::FromArgs;
 use rayon::prelude::*;
-use memmap2::MmapOptions;
+use memmap2::{ Mmap, MmapOptions };
 use flate2::bufread::DeflateDecoder;
 use zstd::stream::read::Decoder as ZstdDecoder;
-use encoding_rs::Encoding;
-use chardetng::EncodingDetector;
-use zip_parser::{ compress, Zip64Archive };
-use util::{ Decoder, Crc32Checker };
-
-use serde::Deserialize;
-use lazy_static::lazy_static;
-use regex::Regex;
-
-/*
-   type SecData struct {
-   Filings struct {
-   Recent SecFilings `json:"recent"`
-   Files []SecFile `json:"files"`
-   } `json:"filings"`
-   }
-   */
-#[derive(Deserialize, Debug)]
-#[serde(rename_all="camelCase")]
-#[allow(dead_code)]
-struct SecData<'a> {
-    #[serde(borrow)]
-    filings: SecRecentFilings<'a>,
-}
-
-#[derive(Deserialize, Debug)]
-#[serde(rename_all="camelCase")]
-#[allow(dead_code)]
-struct SecRecentFilings<'a> {
-    #[serde(borrow)]
-    recent: SecFilings<'a>,
-    #[serde(borrow)]
-    files: Vec<SecFile<'a>>,
-}
-
-#[derive(Deserialize, Debug)]
-#[serde(rename_all="camelCase")]
-#[allow(dead_code)]
-struct SecFile<'a> {
-    name: &'a str,
-    filing_count: i64,
-    filing_from: &'a str,
-    filing_to: &'a str,
-}
-
-#[derive(Deserialize, Debug)]
-#[serde(rename_all="camelCase")]
-#[allow(dead_code)]
-struct SecFilings<'a> {
-    #[serde(borrow)]
-    accession_number: Vec<&'a str>,
-    #[serde(borrow)]
-    filing_date: Vec<&'a str>,
-    #[serde(borrow)]
-    report_date: Vec<&'a str>,
-    #[serde(borrow)]
-    acceptance_date_time: Vec<&'a str>,
-    #[serde(borrow)]
-    act: Vec<&'a str>,
-    #[serde(borrow)]
-    form: Vec<&'a str>,
-    #[serde(borrow)]
-    file_number: Vec<&'a str>,
-    #[serde(borrow)]
-    items: Vec<&'a str>,
-    size: Vec<Option<i64>>,
-    #[serde(rename="isXBRL")]
-    is_xbrl: Vec<i64>,
-    #[serde(rename="isInlineXBRL")]
-    is_inline_xbrl: Vec<i64>,
-    #[serde(borrow)]
-    primary_document: Vec<&'a str>,
-    #[serde(borrow)]
-    primary_doc_description: Vec<Cow<'a, str>>,
-}
-
-/*
-   type SecFiling struct {
-   AccessionNumber string `json:"accessionNumber"`
-   FilingDate string `json:"filingDate"`
-   ReportDate string `json:"reportDate"`
-   AcceptanceDateTime string `json:"acceptanceDateTime"`
-   Act string `json:"act"`
-   Form string `json:"form"`
-   FileNumber string `json:"fileNumber"`
-   Items string `json:"items"`
-   Size int `json:"size"`
-   IsXBRL int `json:"isXBRL"`
-   IsInlineXBRL int `json:"isInlineXBRL"`
-   PrimaryDocument string `json:"primaryDocument"`
-   PrimaryDocDescription string `json:"primaryDocDescription"`
-   }
-   */
-
-
-/// unzipx - extract compressed files in a ZIP archive
+use zip_parser::{ compress, system, Zip64Archive, CentralFileHeader };
+use util::{ Decoder, Crc32Checker, Crc32Mismatch, PartialFile, dos2time, copy_file_range };
+use report::{ Status, Kind, Failure, Report, Tally };
+use filter::{ Filter, Preset };
+use progress::{ Progress, ProgressMode };
+use limits::{ ByteSize, Limits, Budget, MemoryBudget };
+use sanitize::{ Claims, Destination, PathPolicy, Resolver };
+use charset::{ Charset, TextDecoder };
+use order::Reorder;
+use manifest::{ Manifest, Action };
+use sanitize::Issue;
+use sec::{ SecData, SecFile, SecFilings };
+use forms::{ FormType, FormFamily, ItemCode, UnknownItems };
+use export::{ Export, Table, Columns, Compression };
+
+use serde::Serialize;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+
+/// unzipx - extract compressed files in a ZIP archive. `unzipx diff --help` compares two archives.
 #[derive(FromArgs)]
 struct Options {
-    /// path of the ZIP archive(s).
+    /// path of the ZIP archive(s), wildcards in the file name select several archives.
     #[argh(positional)]
-    file: Vec<PathBuf>,
+    file: PathBuf,
+
+    /// archive members to be processed, unzip-style wildcards (`*`, `?`, `[...]`) by default.
+    #[argh(positional)]
+    members: Vec<String>,
+
+    /// exclude archive members that match the pattern, may be given several times.
+    #[argh(option, short = 'x')]
+    exclude: Vec<String>,
+
+    /// interpret member and exclude patterns as regular expressions.
+    #[argh(switch)]
+    regex: bool,
+
+    /// select archive members listed by name in a file, one per line.
+    #[argh(option)]
+    names_from: Option<PathBuf>,
+
+    /// select archive members with a named preset: sec-submissions.
+    #[argh(option)]
+    preset: Option<Preset>,
 
     /// an optional directory to which to extract files.
     #[argh(option, short = 'd')]
     exdir: Option<PathBuf>,
 
-    /// specify character set used to decode filename, which will be automatically detected by default.
-    #[argh(option, short = 'O')]
-    charset: Option<String>
+    /// specify character set used to decode filenames not flagged as UTF-8: auto (detect once per archive, default), cp437 (the ZIP specification default) or any WHATWG encoding label.
+    #[argh(option, short = 'O', default = "Charset::Auto")]
+    charset: Charset,
+
+    /// parse selected members as SEC submissions JSON instead of extracting them.
+    #[argh(switch)]
+    parse: bool,
+
+    /// print the 8-K filings that report an item, like 5.02, may be given several times. implies --parse.
+    /// with --form-family or --annual-reports, filings have to match all of them.
+    #[argh(option)]
+    item: Vec<ItemCode>,
+
+    /// print the filings of a form family: periodic, current, registration, ownership, proxy or fund, may be given several times. implies --parse.
+    /// with --item or --annual-reports, filings have to match all of them.
+    #[argh(option)]
+    form_family: Vec<FormFamily>,
+
+    /// print the annual reports, 10-K, 10-KT, 20-F and 40-F, and their amendments. implies --parse.
+    /// with --item or --form-family, filings have to match all of them.
+    #[argh(switch)]
+    annual_reports: bool,
+
+    /// write the parsed filings, with the company they belong to: ndjson, csv, tsv, parquet or arrow (IPC stream). implies --parse.
+    #[argh(option)]
+    output: Option<export::Format>,
+
+    /// what a row of --output is: filings (default) or companies.
+    #[argh(option, default = "Table::Filings")]
+    table: Table,
+
+    /// comma separated columns of csv and tsv output, like cik,name,form,filing_date.
+    #[argh(option)]
+    columns: Option<Columns>,
+
+    /// file to write --output to, stdout by default.
+    #[argh(option)]
+    output_file: Option<PathBuf>,
+
+    /// compress --output: gzip or zstd, by default from the extension of --output-file, .gz or .zst.
+    #[argh(option)]
+    compress: Option<Compression>,
+
+    /// how to treat member names that aren't plain relative paths: strict, sanitize (default) or warn.
+    #[argh(option, default = "PathPolicy::Sanitize")]
+    path_policy: PathPolicy,
+
+    /// test archive files: decompress and check every entry without writing anything.
+    #[argh(switch, short = 't')]
+    test: bool,
+
+    /// extract files to stdout (pipe), in archive order.
+    #[argh(switch, short = 'p')]
+    pipe: bool,
+
+    /// frame each member piped to stdout: ndjson.
+    #[argh(option)]
+    frame: Option<Frame>,
+
+    /// keep processing the remaining entries after an entry fails.
+    #[argh(switch)]
+    keep_going: bool,
+
+    /// print progress to stderr: bar or json.
+    #[argh(option)]
+    progress: Option<ProgressMode>,
+
+    /// number of worker threads, all cores by default. -j 1 processes entries one by one on the main thread.
+    #[argh(option, short = 'j')]
+    jobs: Option<usize>,
+
+    /// print the outcome of every entry in central directory order, as soon as all entries before it are done.
+    /// archives are processed one at a time.
+    #[argh(switch)]
+    ordered: bool,
+
+    /// stop every archive at the first archive with errors, instead of carrying on with the others.
+    #[argh(switch)]
+    fail_fast: bool,
+
+    /// maximum number of entries to process across all archives, 10000000 by default.
+    #[argh(option)]
+    max_entries: Option<u64>,
+
+    /// maximum uncompressed size across all archives (K, M, G, T suffixes), 1T by default.
+    #[argh(option)]
+    max_total_size: Option<ByteSize>,
+
+    /// maximum uncompressed size of a single entry (K, M, G, T suffixes), 4G by default.
+    #[argh(option)]
+    max_entry_size: Option<ByteSize>,
+
+    /// maximum ratio of uncompressed to compressed size of an entry, 1000 by default.
+    #[argh(option)]
+    max_ratio: Option<u64>,
+
+    /// print what extraction would do as one JSON object per entry, without touching the filesystem.
+    #[argh(switch)]
+    dry_run: bool,
+
+    /// skip entries whose file already holds their exact size and crc32, and replace the files a previous run wrote for other versions of them, according to its manifest.
+    #[argh(switch)]
+    resume: bool,
+
+    /// don't check the crc32 of stored entries, they are copied to disk without being read.
+    #[argh(switch)]
+    trust_stored: bool,
+
+    /// maximum decoded data held in memory at once across workers (K, M, G, T suffixes), 1G by default.
+    #[argh(option)]
+    max_memory: Option<ByteSize>,
+}
+
+/// unzipx diff - compare two archives by entry name, size and crc32
+#[derive(FromArgs)]
+struct DiffOptions {
+    /// path of the older archive.
+    #[argh(positional)]
+    old: PathBuf,
+
+    /// path of the newer archive.
+    #[argh(positional)]
+    new: PathBuf,
+
+    /// output format: text or json, text by default.
+    #[argh(option, default = "diff::Format::Text")]
+    format: diff::Format,
+
+    /// also decompress the entries whose size and crc32 match, and compare their content.
+    #[argh(switch)]
+    verify: bool,
+
+    /// extract the added and modified entries of the newer archive into this directory, replacing the files there.
+    #[argh(option)]
+    extract: Option<PathBuf>,
+
+    /// charset of names that aren't flagged as UTF-8: auto, cp437 or an encoding label, auto by default.
+    #[argh(option, short = 'O', default = "Charset::Auto")]
+    charset: Charset,
 }
 
-fn main() -> anyhow::Result<()> {
-    let options: Options = argh::from_env();
+/// How members written to stdout are kept apart.
+#[derive(Clone, Copy, Debug)]
+enum Frame {
+    /// one JSON object per line, with the name and the content as UTF-8 or base64.
+    Ndjson,
+}
+
+impl std::str::FromStr for Frame {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Frame, String> {
+        match s {
+            "ndjson" => Ok(Frame::Ndjson),
+            _ => Err(format!("unknown frame `{}`, expected one of: ndjson", s))
+        }
+    }
+}
+
+/// Settings shared by every archive of a run.
+struct Config {
+    charset: Charset,
+    filter: Filter,
+    target_dir: PathBuf,
+    frame: Option<Frame>,
+    parse: bool,
+    /// 8-K items to print the filings of, when parsing.
+    items: Vec<ItemCode>,
+    form_families: Vec<FormFamily>,
+    annual_reports: bool,
+    export: Option<Export>,
+    path_policy: PathPolicy,
+    keep_going: bool,
+    progress_mode: Option<ProgressMode>,
+    /// of the whole run, printed by a single watch.
+    progress: Progress,
+    budget: Budget,
+    memory: MemoryBudget,
+    trust_stored: bool,
+    resume: bool,
+    /// replace existing files, even those no manifest record shows are ours.
+    overwrite: bool,
+    /// leave stdout to the data: no archive headers, comments or outcomes of entries.
+    quiet: bool,
+    ordered: bool,
+    /// don't use the rayon pool at all.
+    sequential: bool,
+    fail_fast: bool,
+    /// set once an archive failed under `fail_fast`.
+    abort: AtomicBool,
+    tally: Tally,
+    /// output paths, shared by the archives extracted into `target_dir`.
+    claims: Claims,
+}
+
+impl Config {
+    /// settings for plain extraction into `target_dir`.
+    fn new(target_dir: PathBuf, filter: Filter, limits: Limits) -> Config {
+        Config {
+            charset: Charset::Auto,
+            filter,
+            target_dir,
+            frame: None,
+            parse: false,
+            items: Vec::new(),
+            form_families: Vec::new(),
+            annual_reports: false,
+            export: None,
+            path_policy: PathPolicy::Sanitize,
+            keep_going: false,
+            progress_mode: None,
+            progress: Progress::new(1),
+            budget: Budget::new(limits),
+            memory: MemoryBudget::new(limits.max_memory),
+            trust_stored: false,
+            resume: false,
+            overwrite: false,
+            quiet: false,
+            ordered: false,
+            sequential: false,
+            fail_fast: false,
+            abort: AtomicBool::new(false),
+            tally: Tally::default(),
+            claims: Claims::default(),
+        }
+    }
+
+    /// whether --item, --form-family or --annual-reports pick filings to print.
+    fn selects_filings(&self) -> bool {
+        !self.items.is_empty() || !self.form_families.is_empty() || self.annual_reports
+    }
+}
+
+/// parse the arguments of a subcommand, exiting on `--help` and errors like `argh::from_env`.
+fn subcommand_from_env<T: FromArgs>(name: &str, args: &[String]) -> T {
+    let command = format!("unzipx {}", name);
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match T::from_args(&[&command], &args) {
+        Ok(options) => options,
+        Err(exit) => match exit.status {
+            Ok(()) => {
+                println!("{}", exit.output);
+                std::process::exit(0)
+            },
+            Err(()) => {
+                eprintln!("{}\nRun {} --help for more information.", exit.output, command);
+                std::process::exit(1)
+            }
+        }
+    }
+}
+
+fn main() -> anyhow::Result<ExitCode> {
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(String::as_str) == Some("diff") {
+        let options: DiffOptions = subcommand_from_env("diff", &args[2..]);
+        return Ok(diff(options)?.into())
+    }
+
+    let mut options: Options = argh::from_env();
+    options.parse |= !options.item.is_empty()
+        || !options.form_family.is_empty()
+        || options.annual_reports
+        || options.output.is_some();
+
+    anyhow::ensure!(!(options.test && options.pipe), "-t and -p can't be combined");
+    anyhow::ensure!(options.frame.is_none() || options.pipe, "--frame requires -p");
+    anyhow::ensure!(!(options.parse && (options.test || options.pipe)), "--parse and the options implying it can't be combined with -t or -p");
+    anyhow::ensure!(options.output_file.is_none() || options.output.is_some(), "--output-file requires --output");
+    anyhow::ensure!(options.compress.is_none() || options.output.is_some(), "--compress requires --output");
+    anyhow::ensure!(options.columns.is_none() || matches!(options.output, Some(export::Format::Csv | export::Format::Tsv)),
+        "--columns requires --output csv or tsv");
+    if options.output.is_some_and(export::Format::is_columnar) {
+        anyhow::ensure!(options.table == Table::Filings, "--output parquet and arrow only have the filings table");
+        anyhow::ensure!(options.compress.is_none(), "--compress only applies to --output ndjson, csv and tsv");
+    }
+    anyhow::ensure!(options.jobs != Some(0), "-j must be at least 1");
+    anyhow::ensure!(!options.resume || !(options.test || options.pipe || options.parse), "--resume only applies to extraction");
+    anyhow::ensure!(!options.dry_run || !(options.test || options.pipe || options.parse), "--dry-run only applies to extraction");
 
     let target_dir = if let Some(exdir) = options.exdir {
         exdir
@@ -136,80 +372,1422 @@ fn main() -> anyhow::Result<()> {
         let path = env::current_dir()?;
         PathBuf::from_path_buf(path).ok().context("must utf8 path")?
     };
-    let charset = if let Some(label) = options.charset {
-        Some(Encoding::for_label(label.as_bytes()).context("invalid encoding label")?)
+    // every argument used to be an archive
+    if let Some(member) = options.members.iter().find(|member| is_archive_file(member)) {
+        anyhow::bail!("`{}` is taken for a member of {}, it names an archive: quote a wildcard to process several archives, like 'data/*.zip'",
+            member,
+            options.file
+        );
+    }
+    let mut filter = Filter::new(&options.members, &options.exclude, options.regex)?;
+    if let Some(preset) = options.preset {
+        filter.preset(preset);
+    }
+    if let Some(path) = options.names_from {
+        filter.names_from(&path)?;
+    }
+
+    let defaults = Limits::default();
+    let limits = Limits {
+        max_entries: options.max_entries.unwrap_or(defaults.max_entries),
+        max_total_size: options.max_total_size.unwrap_or(defaults.max_total_size),
+        max_entry_size: options.max_entry_size.unwrap_or(defaults.max_entry_size),
+        max_ratio: options.max_ratio.unwrap_or(defaults.max_ratio),
+        max_memory: options.max_memory.unwrap_or(defaults.max_memory),
+    };
+
+    let export = match options.output {
+        Some(format) => Some(Export::new(
+            format,
+            options.table,
+            options.columns.map(|columns| columns.0),
+            options.compress,
+            options.output_file.as_deref()
+        )?),
+        None => None
+    };
+
+    let archives = filter::glob_archives(&options.file)?;
+    let config = Config {
+        charset: options.charset,
+        frame: options.frame,
+        parse: options.parse,
+        items: options.item,
+        form_families: options.form_family,
+        annual_reports: options.annual_reports,
+        export,
+        path_policy: options.path_policy,
+        // test every entry, like unzip does
+        keep_going: options.keep_going || options.test,
+        progress_mode: options.progress,
+        progress: Progress::new(archives.len() as u64),
+        trust_stored: options.trust_stored,
+        resume: options.resume,
+        // exported filings may go to stdout
+        quiet: options.output.is_some(),
+        ordered: options.ordered,
+        sequential: options.jobs == Some(1),
+        fail_fast: options.fail_fast,
+        ..Config::new(target_dir, filter, limits)
+    };
+    let process: fn(&Config, &Path) -> anyhow::Result<Status> = if options.test {
+        test
+    } else if options.pipe {
+        pipe
+    } else if options.dry_run {
+        dry_run
     } else {
-        None
+        unzip
+    };
+    // tested and parsed archives share the pool with their entries, unless output has to stay in order.
+    // extracted archives share `target_dir`, they go one after the other so the first
+    // to claim a path keeps it, like entries within an archive
+    let parallel = (options.test || options.parse) && !config.ordered && !config.sequential;
+    let run = || config.progress.watch(config.progress_mode, options.file.as_str(), || {
+        run_archives(&config, &archives, process, parallel)
+    });
+
+    let statuses = match options.jobs {
+        Some(jobs) if jobs > 1 => rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs)
+            .build()?
+            .install(run),
+        _ => run()
     };
 
-    for file in options.file.iter() {
-        unzip(charset, &target_dir, file)?;
+    if let Some(export) = &config.export {
+        export.finish().context("write --output")?;
     }
+    if statuses.len() > 1 {
+        let _ = config.tally.write(&mut io::stderr().lock(), &statuses);
+    }
+    let status = statuses.iter()
+        .filter_map(|(_, status)| *status)
+        .max()
+        .unwrap_or(Status::Ok);
 
-    Ok(())
+    Ok(status.into())
+}
+
+/// process every archive with `process`, across the rayon pool if `parallel` is set.
+///
+/// the status of each archive, `None` if it was skipped after another one failed under `fail_fast`.
+fn run_archives<'a>(
+    config: &Config,
+    archives: &'a [PathBuf],
+    process: fn(&Config, &Path) -> anyhow::Result<Status>,
+    parallel: bool
+) -> Vec<(&'a PathBuf, Option<Status>)> {
+    let run_archive = |file: &PathBuf| -> Option<Status> {
+        if config.abort.load(Ordering::Relaxed) {
+            return None
+        }
+
+        // one archive going wrong doesn't stop the others
+        let status = process(config, file).unwrap_or_else(|err| {
+            eprintln!("unzipx: {}: {:#}", file, err);
+            Status::Severe
+        });
+
+        if config.fail_fast && status > Status::Warning {
+            config.abort.store(true, Ordering::Relaxed);
+        }
+        Some(status)
+    };
+
+    if parallel {
+        archives.par_iter().map(|file| (file, run_archive(file))).collect()
+    } else {
+        archives.iter().map(|file| (file, run_archive(file))).collect()
+    }
+}
+
+/// whether a member pattern looks like it was meant for an archive, an existing `.zip` file.
+fn is_archive_file(member: &str) -> bool {
+    member.to_ascii_lowercase().ends_with(".zip") && Path::new(member).is_file()
+}
+
+type EntryReader<'a> = Crc32Checker<io::Take<Decoder<&'a [u8]>>>;
+
+fn open_entry<'a>(zip: &'a Zip64Archive, cfh: &CentralFileHeader, name: &str)
+    -> Result<EntryReader<'a>, Failure>
+{
+    let (_, buf) = zip.read(cfh)
+        .map_err(|err| Failure::new(name, Kind::LocalHeader, err))?;
+
+    let reader = match cfh.method {
+        compress::STORE => Decoder::None(buf),
+        compress::DEFLATE => Decoder::Deflate(DeflateDecoder::new(buf)),
+        compress::ZSTD => Decoder::Zstd(ZstdDecoder::with_buffer(buf)
+            .map_err(|err| Failure::new(name, Kind::Decoder, err))?),
+        method => return Err(Failure::new(name, Kind::Unsupported,
+            format_args!("unsupported compression method {}", method))),
+    };
+    // prevent zipbomb
+    let reader = reader.take(cfh.uncomp_size.into());
+    Ok(Crc32Checker::new(reader, cfh.crc32))
+}
+
+/// decompress a whole entry into memory, reserve it from `Config::memory` first.
+fn read_entry(zip: &Zip64Archive, cfh: &CentralFileHeader, name: &str) -> Result<Vec<u8>, Failure> {
+    // don't trust the declared size for the initial allocation
+    const MAX_CAPACITY: u32 = 64 * 1024 * 1024;
+
+    let mut reader = open_entry(zip, cfh, name)?;
+    let mut data = Vec::with_capacity(cfh.uncomp_size.min(MAX_CAPACITY) as usize);
+    reader.read_to_end(&mut data)
+        .map_err(|err| Failure::read(name, err))?;
+    Ok(data)
+}
+
+/// An archive mapped into memory, with the file it's mapped from.
+struct MappedArchive {
+    fd: fs::File,
+    map: Mmap,
+}
+
+impl MappedArchive {
+    /// position in the file of `data`, a slice of the map.
+    fn offset_of(&self, data: &[u8]) -> u64 {
+        (data.as_ptr() as usize - self.map.as_ptr() as usize) as u64
+    }
+}
+
+/// map an archive into memory, reporting a missing file the way unzip does.
+fn map_archive(path: &Path) -> Result<MappedArchive, Status> {
+    let archive = fs::File::open(path).and_then(|fd| {
+        let map = unsafe { MmapOptions::new().map_copy_read_only(&fd)? };
+        Ok(MappedArchive { fd, map })
+    });
+
+    archive.map_err(|err| {
+        eprintln!("unzipx: cannot find or open {}: {}", path, err);
+        Status::NotFound
+    })
 }
 
-fn unzip(charset: Option<&'static Encoding>, target_dir: &Path, path: &Path) -> anyhow::Result<()> {
-    lazy_static! {
-        static ref RE: Regex = Regex::new(r"^CIK\d{10}.json$").unwrap();
+fn parse_archive<'a>(buf: &'a [u8], path: &Path) -> Result<Zip64Archive<'a>, Status> {
+    Zip64Archive::parse(buf).map_err(|err| {
+        eprintln!("unzipx: cannot find zipfile directory in {}: {}", path, err);
+        Status::NotFound
+    })
+}
+
+/// set up name decoding for an archive, and tell which charset was detected.
+fn new_text_decoder(config: &Config, zip: &Zip64Archive, path: &Path) -> anyhow::Result<TextDecoder> {
+    let text = TextDecoder::new(config.charset, zip)?;
+    if let Some(encoding) = text.detected() {
+        eprintln!("unzipx: {}: decoding names that aren't UTF-8 as {}", path, encoding);
+    }
+    Ok(text)
+}
+
+fn print_archive_comment(text: &TextDecoder, zip: &Zip64Archive) {
+    let comment = text.archive_comment(zip);
+    if !comment.is_empty() {
+        println!("{}", comment);
+    }
+}
+
+/// warn about member patterns that match no entry of the archive, like unzip does.
+fn check_unmatched(config: &Config, zip: &Zip64Archive, text: &TextDecoder, path: &Path) -> anyhow::Result<Status> {
+    let names = zip.entries()?.filter_map(Result::ok).map(|cfh| text.name(&cfh));
+    let unmatched = config.filter.unmatched(names);
+    for pattern in unmatched.iter() {
+        eprintln!("unzipx: {}: caution: filename not matched:  {}", path, pattern);
+    }
+    Ok(if unmatched.is_empty() { Status::Ok } else { Status::NoMatch })
+}
+
+/// count an archive in the progress of the run.
+fn start_progress<'a>(config: &'a Config, zip: &Zip64Archive) -> &'a Progress {
+    let eocdr = zip.zip64_eocdr();
+    config.progress.add_archive(eocdr.cd_entries, eocdr.cd_offset);
+    &config.progress
+}
+
+fn print_outcome(verb: &str, outcome: &Result<String, Failure>) {
+    match outcome {
+        Ok(name) => println!("{:>11}: {}", verb, name),
+        Err(failure) => println!("{:>11}: {}  [{}] {}", verb, failure.name, failure.kind, failure.message),
+    }
+}
+
+/// run `f` on every selected entry across the rayon pool and gather the failures.
+/// `f` is given the index of the entry in the central directory.
+///
+/// unless `keep_going` is set, entries not yet started are skipped after the first failure.
+/// with `ordered`, the outcome of every entry is printed as `verb: name` in central directory order,
+/// unless stdout is left to the data.
+fn for_each_entry<F>(config: &Config, zip: &Zip64Archive, text: &TextDecoder, verb: &str, f: F)
+    -> anyhow::Result<Report>
+where
+    F: Fn(usize, &CentralFileHeader, &str) -> Result<(), Failure> + Sync
+{
+    let processed = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let progress = start_progress(config, zip);
+    let entries = zip.entries()?;
+
+    // the name on success, `None` for skipped entries
+    let run = |(index, cfh): (usize, Result<CentralFileHeader, zip_parser::Error>)| -> Option<Result<String, Failure>> {
+        if stop.load(Ordering::Relaxed) || config.abort.load(Ordering::Relaxed) {
+            return None
+        }
+
+        let outcome = match cfh {
+            Ok(cfh) => {
+                let name = text.name(&cfh);
+                // pages are parsed with the company they belong to, and admitted there
+                if !config.filter.is_match(&name) || (config.parse && sec::is_page(&name)) {
+                    progress.add(cfh.comp_size.into(), 0);
+                    return None
+                }
+                processed.fetch_add(1, Ordering::Relaxed);
+                let result = config.budget.admit(&cfh, &name)
+                    .and_then(|_| f(index, &cfh, &name));
+                progress.add(cfh.comp_size.into(), cfh.uncomp_size.into());
+                result.map(|_| name.into_owned())
+            },
+            Err(err) => Err(Failure::new("<central directory>", Kind::CentralDirectory, err))
+        };
+
+        if outcome.is_err() && !config.keep_going {
+            stop.store(true, Ordering::Relaxed);
+        }
+        Some(outcome)
+    };
+
+    let failures: Vec<Failure> = if config.ordered {
+        let reorder = Reorder::new(rayon::current_num_threads() * 16);
+        let failures = Mutex::new(Vec::new());
+
+        let run_ordered = |(index, cfh)| {
+            reorder.wait_turn(index);
+            let outcome = run((index, cfh));
+            reorder.push(index, outcome, |outcome| if let Some(outcome) = outcome {
+                if !config.quiet {
+                    print_outcome(verb, &outcome);
+                }
+                if let Err(failure) = outcome {
+                    failures.lock().unwrap().push(failure);
+                }
+            });
+        };
+
+        if config.sequential {
+            entries.enumerate().for_each(run_ordered);
+        } else {
+            entries.enumerate().par_bridge().for_each(run_ordered);
+        }
+        failures.into_inner().unwrap()
+    } else if config.sequential {
+        entries.enumerate().filter_map(run).filter_map(Result::err).collect()
+    } else {
+        entries.enumerate().par_bridge().filter_map(run).filter_map(Result::err).collect()
+    };
+
+    let mut report = Report {
+        processed: processed.into_inner(),
+        failures,
+        stopped: stop.into_inner(),
+        printed: config.ordered && !config.quiet,
+    };
+    config.tally.add(report.processed, report.failures.len());
+    // already in central directory order
+    if !config.ordered {
+        report.sort();
     }
+    Ok(report)
+}
+
+/// write lines to stdout at once, so they don't interleave with those of other archives.
+fn print_block(block: &str) {
+    let _ = io::stdout().lock().write_all(block.as_bytes());
+}
 
-    println!("Archive: {}", path);
+fn test(config: &Config, path: &Path) -> anyhow::Result<Status> {
+    // archives are tested concurrently, the lines of each one are printed together
+    let mut out = format!("Archive: {}\n", path);
+    let status = test_archive(config, path, &mut out);
+    print_block(&out);
+    status
+}
 
-    let fd = fs::File::open(path)?;
-    let buf = unsafe {
-        MmapOptions::new().map_copy_read_only(&fd)?
+fn test_archive(config: &Config, path: &Path, out: &mut String) -> anyhow::Result<Status> {
+    let archive = match map_archive(path) {
+        Ok(archive) => archive,
+        Err(status) => return Ok(status)
+    };
+    let zip = match parse_archive(&archive.map, path) {
+        Ok(zip) => zip,
+        Err(status) => return Ok(status)
     };
+    let text = new_text_decoder(config, &zip, path)?;
+    let comment = text.archive_comment(&zip);
+    if !comment.is_empty() {
+        out.push_str(&format!("{}\n", comment));
+    }
 
-    /*
-       let buf = fs::read(path)?;
-       println!("read");
-       */
+    // outcomes are printed as they happen, archives are tested one at a time then
+    if config.ordered {
+        print_block(out);
+        out.clear();
+    }
+    let report = for_each_entry(config, &zip, &text, "testing", |_, cfh, name| {
+        let mut reader = open_entry(&zip, cfh, name)?;
+        io::copy(&mut reader, &mut io::sink())
+            .map_err(|err| Failure::read(name, err))?;
+        Ok(())
+    })?;
+    let unmatched = check_unmatched(config, &zip, &text, path)?;
+
+    if report.failures.is_empty() {
+        out.push_str(&format!("No errors detected in compressed data of {}.\n", path));
+        return Ok(unmatched)
+    }
 
-    let zip = Zip64Archive::parse(&buf)?;
-    zip.entries()?.par_bridge().for_each(|cfh| {
-        let cfh = cfh.expect("didn't get a cfh");
-        let (_, buf) = zip.read(&cfh).expect("couldn't read");
+    if !report.printed {
+        for failure in report.failures.iter() {
+            out.push_str(&format!("    testing: {}\n", failure));
+        }
+    }
+    out.push_str(&format!("At least one error was detected in {}.\n", path));
 
-        let name = if let Some(encoding) = charset {
-            let (name, ..) = encoding.decode(cfh.name);
-            name
-        } else if let Ok(name) = std::str::from_utf8(cfh.name) {
-            Cow::Borrowed(name)
+    Ok(report.status().max(unmatched))
+}
+
+#[derive(Serialize)]
+struct FramedMember<'a> {
+    name: &'a str,
+    encoding: &'static str,
+    content: Cow<'a, str>,
+}
+
+fn write_member(out: &mut impl Write, frame: Option<Frame>, name: &str, data: &[u8]) -> io::Result<()> {
+    match frame {
+        None => out.write_all(data),
+        Some(Frame::Ndjson) => {
+            let member = match std::str::from_utf8(data) {
+                Ok(content) => FramedMember { name, encoding: "utf-8", content: Cow::Borrowed(content) },
+                Err(_) => FramedMember { name, encoding: "base64", content: Cow::Owned(BASE64.encode(data)) },
+            };
+            serde_json::to_writer(&mut *out, &member)?;
+            out.write_all(b"\n")
+        }
+    }
+}
+
+/// decompress selected members to stdout.
+///
+/// members are decoded in parallel a batch at a time, and written out in
+/// central directory order once the whole batch is done. a batch holds as many
+/// members as fit in the memory budget, and at least one.
+fn pipe(config: &Config, path: &Path) -> anyhow::Result<Status> {
+    let archive = match map_archive(path) {
+        Ok(archive) => archive,
+        Err(status) => return Ok(status)
+    };
+    let zip = match parse_archive(&archive.map, path) {
+        Ok(zip) => zip,
+        Err(status) => return Ok(status)
+    };
+    let text = new_text_decoder(config, &zip, path)?;
+    let progress = start_progress(config, &zip);
+
+    let status = check_unmatched(config, &zip, &text, path)?;
+    // failures are printed as they happen, members before them are written out already
+    let mut report = Report { printed: true, ..Report::default() };
+    let fail = |report: &mut Report, failure: Failure| {
+        eprintln!("unzipx: [{}] {}", failure.kind, failure);
+        report.failures.push(failure);
+        // like `for_each_entry`, members after the first failure are skipped
+        report.stopped = !config.keep_going;
+    };
+
+    let mut members = Vec::new();
+    for cfh in zip.entries()? {
+        let cfh = match cfh {
+            Ok(cfh) => cfh,
+            Err(err) => {
+                fail(&mut report, Failure::new("<central directory>", Kind::CentralDirectory, err));
+                break
+            }
+        };
+        let name = text.name(&cfh);
+        if config.filter.is_match(&name) {
+            if let Err(failure) = config.budget.admit(&cfh, &name) {
+                report.processed += 1;
+                fail(&mut report, failure);
+                progress.add(cfh.comp_size.into(), 0);
+                if report.stopped {
+                    break
+                }
+                continue
+            }
+            members.push((cfh, name));
         } else {
-            let mut encoding_detector = EncodingDetector::new();
-            encoding_detector.feed(cfh.name, true);
-            let (name, ..) = encoding_detector.guess(None, false).decode(cfh.name);
-            name
+            progress.add(cfh.comp_size.into(), 0);
+        }
+    }
+
+    let mut write_members = || {
+        let stdout = io::stdout();
+        let mut stdout = io::BufWriter::new(stdout.lock());
+        let batch_size = rayon::current_num_threads() * 4;
+
+        let mut rest = &members[..];
+        while let Some((first, _)) = rest.first() {
+            let mut reservations = vec![config.memory.acquire(first.uncomp_size.into())];
+            while reservations.len() < batch_size.min(rest.len()) {
+                let (cfh, _) = &rest[reservations.len()];
+                match config.memory.try_acquire(cfh.uncomp_size.into()) {
+                    Some(reservation) => reservations.push(reservation),
+                    None => break
+                }
+            }
+            let (batch, next) = rest.split_at(reservations.len());
+            rest = next;
+
+            let read = |(cfh, name): &(CentralFileHeader, Cow<str>)| {
+                let data = read_entry(&zip, cfh, name)?;
+                progress.add(cfh.comp_size.into(), cfh.uncomp_size.into());
+                Ok(data)
+            };
+            let results: Vec<Result<Vec<u8>, Failure>> = if config.sequential {
+                batch.iter().map(read).collect()
+            } else {
+                batch.par_iter().map(read).collect()
+            };
+
+            for ((_, name), result) in batch.iter().zip(results) {
+                report.processed += 1;
+                let data = match result {
+                    Ok(data) => data,
+                    Err(failure) => {
+                        fail(&mut report, failure);
+                        if report.stopped {
+                            rest = &[];
+                            break
+                        }
+                        continue
+                    }
+                };
+
+                match write_member(&mut stdout, config.frame, name, &data) {
+                    Ok(()) => (),
+                    // reader went away, e.g. `| head`
+                    Err(err) if err.kind() == io::ErrorKind::BrokenPipe => return Ok(()),
+                    Err(err) => return Err(err)
+                }
+            }
+        }
+
+        match stdout.flush() {
+            Err(err) if err.kind() != io::ErrorKind::BrokenPipe => Err(err),
+            _ => Ok(())
+        }
+    };
+    let written = write_members();
+
+    config.tally.add(report.processed, report.failures.len());
+    report.eprint(path);
+    written?;
+    Ok(status.max(report.status()))
+}
+
+/// write an entry out to `path`, with its modification time and unix permissions.
+///
+/// stored entries are copied from the archive file, others are streamed through
+/// a buffer of at most `STREAM_BUFFER` bytes. the file is removed again unless it's persisted.
+fn extract_entry<'a>(
+    config: &Config,
+    archive: &MappedArchive,
+    zip: &Zip64Archive,
+    cfh: &CentralFileHeader,
+    name: &str,
+    path: &'a Path
+) -> Result<PartialFile<'a>, Failure> {
+    // a stored entry whose sizes disagree is broken, the decoding path reports it
+    let file = if cfh.method == compress::STORE && cfh.comp_size == cfh.uncomp_size {
+        write_stored(config, archive, zip, cfh, name, path)?
+    } else {
+        write_decoded(config, zip, cfh, name, path)?
+    };
+
+    if let Ok(mtime) = dos2time(cfh.mod_date, cfh.mod_time) {
+        let mtime = filetime::FileTime::from_unix_time(mtime.assume_utc().unix_timestamp(), 0);
+        filetime::set_file_handle_times(&file.fd, None, Some(mtime)).map_err(|err| Failure::write(name, path, err))?;
+    }
+
+    #[cfg(unix)]
+    if cfh.made_by_ver >> 8 == system::UNIX {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = (cfh.ext_attrs >> 16) & 0o777;
+        if mode != 0 {
+            file.fd.set_permissions(fs::Permissions::from_mode(mode)).map_err(|err| Failure::write(name, path, err))?;
+        }
+    }
+
+    Ok(file)
+}
+
+/// copy a stored entry in the kernel, or from the map where that isn't supported.
+fn write_stored<'a>(
+    config: &Config,
+    archive: &MappedArchive,
+    zip: &Zip64Archive,
+    cfh: &CentralFileHeader,
+    name: &str,
+    path: &'a Path
+) -> Result<PartialFile<'a>, Failure> {
+    let (_, data) = zip.read(cfh)
+        .map_err(|err| Failure::new(name, Kind::LocalHeader, err))?;
+
+    if !config.trust_stored {
+        let crc = crc32fast::hash(data);
+        if crc != cfh.crc32 {
+            let err = Crc32Mismatch { expect: cfh.crc32, got: crc };
+            return Err(Failure::read(name, io::Error::new(io::ErrorKind::InvalidData, err)))
+        }
+    }
+
+    let mut file = PartialFile::create(path).map_err(|err| Failure::write(name, path, err))?;
+    let copied = copy_file_range(&archive.fd, archive.offset_of(data), &file.fd, data.len() as u64)
+        .map_err(|err| Failure::write(name, path, err))?;
+    if !copied {
+        file.fd.write_all(data).map_err(|err| Failure::write(name, path, err))?;
+    }
+
+    Ok(file)
+}
+
+fn write_decoded<'a>(config: &Config, zip: &Zip64Archive, cfh: &CentralFileHeader, name: &str, path: &'a Path)
+    -> Result<PartialFile<'a>, Failure>
+{
+    const STREAM_BUFFER: u32 = 256 * 1024;
+
+    let mut reader = open_entry(zip, cfh, name)?;
+    let mut file = PartialFile::create(path).map_err(|err| Failure::write(name, path, err))?;
+
+    let buf_size = cfh.uncomp_size.clamp(1, STREAM_BUFFER);
+    let _reservation = config.memory.acquire(buf_size.into());
+    let mut buf = vec![0; buf_size as usize];
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(Failure::read(name, err))
         };
+        file.fd.write_all(&buf[..n]).map_err(|err| Failure::write(name, path, err))?;
+    }
 
-        if !RE.is_match(&name) {
-            return
+    Ok(file)
+}
+
+/// the `files` pages of an archive by name, to find them from the company file listing them.
+fn page_index<'a>(zip: &'a Zip64Archive, text: &TextDecoder) -> anyhow::Result<HashMap<String, CentralFileHeader<'a>>> {
+    let mut pages = HashMap::new();
+    for cfh in zip.entries()? {
+        let cfh = cfh?;
+        let name = text.name(&cfh);
+        if sec::is_page(&name) {
+            pages.insert(name.into_owned(), cfh);
         }
+    }
+    Ok(pages)
+}
+
+fn parse_entry(
+    config: &Config,
+    zip: &Zip64Archive,
+    pages: &HashMap<String, CentralFileHeader>,
+    unknown_items: &UnknownItems,
+    cfh: &CentralFileHeader,
+    name: &str
+) -> Result<(), Failure> {
+    // the company is read with its size reserved, and its pages reserved on top without waiting,
+    // waiting while holding a reservation could deadlock. if they don't fit, the company is
+    // given back and read again once everything fits at once.
+    let mut reserve = u64::from(cfh.uncomp_size);
+    loop {
+        let _reservation = config.memory.acquire(reserve);
+        let mut data = read_entry(zip, cfh, name)?;
 
-        let reader = match cfh.method {
-            compress::STORE => Decoder::None(buf),
-            compress::DEFLATE => Decoder::Deflate(DeflateDecoder::new(buf)),
-            compress::ZSTD => Decoder::Zstd(ZstdDecoder::with_buffer(buf).expect("couldn't create zstd decoder")),
-            _ => panic!("idk"),
+        let sec: SecData = simd_json::serde::from_slice(&mut data)
+            .map_err(|err| Failure::new(name, Kind::Parse, err))?;
+
+        let listed = sec.filings.files.iter()
+            .map(|file| match pages.get(file.name) {
+                Some(page) => Ok((file, page)),
+                None => Err(Failure::new(name, Kind::Parse, format_args!("page {} not found", file.name)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let size = u64::from(cfh.uncomp_size) + listed.iter().map(|(_, page)| u64::from(page.uncomp_size)).sum::<u64>();
+        let _pages_reservation = match config.memory.try_acquire(size.saturating_sub(reserve)) {
+            Some(reservation) => reservation,
+            None => {
+                reserve = size;
+                continue
+            }
         };
-        // prevent zipbomb
-        let reader = reader.take(cfh.uncomp_size.into());
-        let mut reader = Crc32Checker::new(reader, cfh.crc32);
 
-        let mut data = Vec::with_capacity(cfh.uncomp_size.try_into().unwrap());
-        reader.read_to_end(&mut data).expect("read error");
+        for (file, page) in listed.iter() {
+            config.budget.admit(page, file.name)?;
+        }
+        return parse_company(config, zip, unknown_items, name, &sec, &listed)
+    }
+}
 
-        let _: SecData = match simd_json::serde::from_slice(&mut data) {
-            Ok(res) => {
-                res
-            },
-            Err(e) => {
-                println!("{}", e);
-                return
-            },
+/// read the pages of a company, then print or export the filings selected from its history.
+fn parse_company(
+    config: &Config,
+    zip: &Zip64Archive,
+    unknown_items: &UnknownItems,
+    name: &str,
+    sec: &SecData,
+    listed: &[(&SecFile, &CentralFileHeader)]
+) -> Result<(), Failure> {
+    let mut buffers = listed.iter()
+        .map(|(file, page)| read_entry(zip, page, file.name))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut parsed = Vec::with_capacity(buffers.len());
+    for ((file, _), buf) in listed.iter().zip(buffers.iter_mut()) {
+        let page: SecFilings = simd_json::serde::from_slice(buf)
+            .map_err(|err| Failure::new(file.name, Kind::Parse, err))?;
+        file.check(&page)
+            .map_err(|err| Failure::new(file.name, Kind::Parse, err))?;
+        parsed.push(page);
+    }
+
+    // this also checks the columns line up
+    let history = sec::history(&sec.filings.recent, &parsed)
+        .map_err(|err| Failure::new(name, Kind::Parse, err))?;
+
+    let mut selected = Vec::with_capacity(history.rows.len());
+    for &row in &history.rows {
+        let filing = history.filing(row);
+        let form_type = &filing.form.form_type;
+        if !config.form_families.is_empty() && !form_type.family().is_some_and(|family| config.form_families.contains(&family)) {
+            continue
+        }
+        if config.annual_reports && !form_type.is_annual_report() {
+            continue
+        }
+        if config.items.is_empty() {
+            selected.push(row);
+            continue
+        }
+        if *form_type != FormType::Form8K {
+            continue
+        }
+        let items = match filing.items() {
+            Ok(items) => items,
+            // one odd filing shouldn't fail the whole company, they are summed up for the archive
+            Err(err) => {
+                unknown_items.add(&err.input);
+                continue
+            }
         };
-    });
+        if items.iter().any(|item| config.items.contains(item)) {
+            selected.push(row);
+        }
+    }
+
+    if let Some(export) = &config.export {
+        export.company(sec, &history, &selected)
+            .map_err(|err| Failure::new(name, Kind::Write, err))?;
+    } else if config.selects_filings() {
+        let mut out = String::new();
+        for filing in selected.into_iter().map(|row| history.filing(row)) {
+            out.push_str(&format!("{}\t{}\t{}\t{}\t{}\n",
+                sec.cik, filing.accession_number, filing.form, filing.filing_date, filing.items));
+        }
+        // one write per company keeps the lines of parallel workers apart
+        io::stdout().lock().write_all(out.as_bytes())
+            .map_err(|err| Failure::new(name, Kind::Write, err))?;
+    }
 
     Ok(())
 }
+
+/// the destinations of the selected entries, by central directory index.
+///
+/// names are resolved one after the other before extracting, so of the entries
+/// whose paths collide it's always the first in the central directory that is extracted.
+fn resolve_entries(config: &Config, zip: &Zip64Archive, text: &TextDecoder, resolver: &Resolver)
+    -> anyhow::Result<Vec<Option<Result<Destination, Failure>>>>
+{
+    let destinations = zip.entries()?
+        .map(|cfh| {
+            // `for_each_entry` reports central directory errors
+            let cfh = cfh.ok()?;
+            let name = text.name(&cfh);
+            config.filter.is_match(&name).then(|| resolver.resolve(&name))
+        })
+        .collect();
+    Ok(destinations)
+}
+
+fn unzip(config: &Config, path: &Path) -> anyhow::Result<Status> {
+    if !config.quiet {
+        println!("Archive: {}", path);
+    }
+
+    let archive = match map_archive(path) {
+        Ok(archive) => archive,
+        Err(status) => return Ok(status)
+    };
+    let zip = match parse_archive(&archive.map, path) {
+        Ok(zip) => zip,
+        Err(status) => return Ok(status)
+    };
+    let text = new_text_decoder(config, &zip, path)?;
+    if !config.quiet {
+        print_archive_comment(&text, &zip);
+    }
+
+    if config.parse {
+        let pages = page_index(&zip, &text)?;
+        let unknown_items = UnknownItems::default();
+        let report = for_each_entry(config, &zip, &text, "parsing", |_, cfh, name| {
+            parse_entry(config, &zip, &pages, &unknown_items, cfh, name)
+        })?;
+        unknown_items.eprint(path);
+        report.eprint(path);
+        return Ok(report.status().max(check_unmatched(config, &zip, &text, path)?))
+    }
+
+    let resolver = Resolver::new(&config.target_dir, config.path_policy, &config.claims, path);
+    let destinations = resolve_entries(config, &zip, &text, &resolver)?;
+    let manifest = Manifest::open(&config.target_dir, path, config.resume, config.overwrite)?;
+    let report = for_each_entry(config, &zip, &text, "extracting", |index, cfh, name| {
+        let dest = match &destinations[index] {
+            Some(Ok(dest)) => dest,
+            Some(Err(failure)) => return Err(failure.clone()),
+            None => unreachable!("`{}` is selected but wasn't resolved", name)
+        };
+        match manifest.plan(cfh, name, dest) {
+            // the rename replaces the file of the previous run
+            Action::Create | Action::Rewrite => (),
+            Action::Skip => {
+                manifest.skip();
+                return Ok(())
+            },
+            Action::Exists => return Err(Failure::write(name, &dest.path, io::ErrorKind::AlreadyExists.into())),
+        }
+
+        if dest.is_dir {
+            return fs::create_dir_all(&dest.path).map_err(|err| Failure::write(name, &dest.path, err))
+        }
+
+        // the destination only ever holds complete files
+        let part = manifest::part_path(&dest.path);
+        let file = extract_entry(config, &archive, &zip, cfh, name, &part)?;
+        file.persist(&dest.path)
+            .map_err(|err| Failure::write(name, &dest.path, err))?;
+        manifest.record(cfh, name)
+            .map_err(|err| Failure::write(name, &manifest.path, err))
+    })?;
+
+    if manifest.skipped() != 0 {
+        eprintln!("unzipx: {}: skipped {} entries extracted by a previous run", path, manifest.skipped());
+    }
+    resolver.eprint();
+    report.eprint(path);
+    let unmatched = check_unmatched(config, &zip, &text, path)?;
+    Ok(report.status().max(resolver.status()).max(unmatched))
+}
+
+/// What extracting an entry would do, a line of `--dry-run`.
+#[derive(Serialize)]
+struct PlannedEntry<'a> {
+    archive: &'a str,
+    name: &'a str,
+    /// what the name was decoded with.
+    encoding: &'static str,
+    /// detected for the names of the archive that aren't UTF-8, if any.
+    detected_encoding: Option<&'static str>,
+    #[serde(skip_serializing_if = "str::is_empty")]
+    comment: Cow<'a, str>,
+    path: Option<&'a str>,
+    is_dir: bool,
+    action: Option<Action>,
+    sanitized: Vec<String>,
+    size: u64,
+    compressed_size: u64,
+    method: u16,
+    crc32: u32,
+    /// unix permissions in octal.
+    mode: Option<String>,
+    /// local time, as stored in the archive.
+    mtime: Option<String>,
+    /// why the entry wouldn't be extracted.
+    error: Option<String>,
+}
+
+/// print the plan for extracting an archive, in central directory order.
+fn dry_run(config: &Config, path: &Path) -> anyhow::Result<Status> {
+    let stdout = io::stdout();
+    let mut stdout = io::BufWriter::new(stdout.lock());
+    let status = write_plan(config, path, &mut stdout)?;
+
+    match stdout.flush() {
+        Err(err) if err.kind() != io::ErrorKind::BrokenPipe => Err(err.into()),
+        _ => Ok(status)
+    }
+}
+
+/// write the plan for extracting an archive to `out`, one JSON line per selected entry.
+///
+/// destinations and actions come from the same `Resolver` and `Manifest` as extraction,
+/// limits are admitted against the same budget.
+fn write_plan(config: &Config, path: &Path, out: &mut impl Write) -> anyhow::Result<Status> {
+    let archive = match map_archive(path) {
+        Ok(archive) => archive,
+        Err(status) => return Ok(status)
+    };
+    let zip = match parse_archive(&archive.map, path) {
+        Ok(zip) => zip,
+        Err(status) => return Ok(status)
+    };
+    let text = new_text_decoder(config, &zip, path)?;
+    let resolver = Resolver::new(&config.target_dir, config.path_policy, &config.claims, path);
+    let manifest = Manifest::inspect(&config.target_dir, path, config.resume, config.overwrite)?;
+    let mut status = check_unmatched(config, &zip, &text, path)?;
+
+    for cfh in zip.entries()? {
+        let cfh = match cfh {
+            Ok(cfh) => cfh,
+            Err(err) => {
+                eprintln!("unzipx: {}: {}", path, err);
+                status = status.max(Kind::CentralDirectory.status());
+                break
+            }
+        };
+        let name = text.name(&cfh);
+        if !config.filter.is_match(&name) {
+            continue
+        }
+
+        // every selected entry claims its path, as in `resolve_entries`
+        let dest = resolver.resolve(&name);
+        let dest = config.budget.admit(&cfh, &name).and(dest);
+        let (dest, error) = match dest {
+            Ok(dest) => (Some(dest), None),
+            Err(failure) => {
+                status = status.max(failure.kind.status());
+                (None, Some(format!("[{}] {}", failure.kind, failure.message)))
+            }
+        };
+        config.tally.add(1, error.is_some().into());
+
+        let action = dest.as_ref().map(|dest| manifest.plan(&cfh, &name, dest));
+        if action == Some(Action::Exists) {
+            // extraction would fail to write it
+            status = status.max(Kind::Write.status());
+        }
+
+        let mode = (cfh.ext_attrs >> 16) & 0o777;
+        let mtime = dos2time(cfh.mod_date, cfh.mod_time).ok();
+        let entry = PlannedEntry {
+            archive: path.as_str(),
+            name: &name,
+            encoding: text.name_encoding(&cfh),
+            detected_encoding: text.detected(),
+            comment: text.comment(&cfh),
+            action,
+            path: dest.as_ref().map(|dest| dest.path.as_str()),
+            is_dir: dest.as_ref().map_or(name.ends_with('/'), |dest| dest.is_dir),
+            sanitized: dest.as_ref()
+                .map(|dest| dest.issues.iter().map(Issue::to_string).collect())
+                .unwrap_or_default(),
+            size: cfh.uncomp_size.into(),
+            compressed_size: cfh.comp_size.into(),
+            method: cfh.method,
+            crc32: cfh.crc32,
+            mode: (cfh.made_by_ver >> 8 == system::UNIX && mode != 0).then(|| format!("{:04o}", mode)),
+            mtime: mtime.map(|mtime| format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+                mtime.year(),
+                u8::from(mtime.month()),
+                mtime.day(),
+                mtime.hour(),
+                mtime.minute(),
+                mtime.second()
+            )),
+            error,
+        };
+
+        let written = serde_json::to_writer(&mut *out, &entry)
+            .map_err(io::Error::from)
+            .and_then(|_| out.write_all(b"\n"));
+        match written {
+            Ok(()) => (),
+            Err(err) if err.kind() == io::ErrorKind::BrokenPipe => return Ok(status),
+            Err(err) => return Err(err.into())
+        }
+    }
+
+    Ok(status.max(resolver.status()))
+}
+
+/// fill `buf` as far as `reader` goes.
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+            Err(err) => return Err(err)
+        }
+    }
+    Ok(filled)
+}
+
+/// decompress two entries side by side and compare them.
+fn same_content(old_zip: &Zip64Archive, old: &CentralFileHeader, new_zip: &Zip64Archive, new: &CentralFileHeader, name: &str)
+    -> Result<bool, Failure>
+{
+    const CHUNK: usize = 64 * 1024;
+
+    let mut old_reader = open_entry(old_zip, old, name)?;
+    let mut new_reader = open_entry(new_zip, new, name)?;
+    let mut old_buf = vec![0; CHUNK];
+    let mut new_buf = vec![0; CHUNK];
+
+    loop {
+        let old_n = read_full(&mut old_reader, &mut old_buf).map_err(|err| Failure::read(name, err))?;
+        let new_n = read_full(&mut new_reader, &mut new_buf).map_err(|err| Failure::read(name, err))?;
+        if old_buf[..old_n] != new_buf[..new_n] {
+            return Ok(false)
+        }
+        if old_n == 0 {
+            return Ok(true)
+        }
+    }
+}
+
+/// compare two archives, and extract the entries that are new or changed in the newer one if asked.
+fn diff(options: DiffOptions) -> anyhow::Result<Status> {
+    let (old_archive, new_archive) = match (map_archive(&options.old), map_archive(&options.new)) {
+        (Ok(old), Ok(new)) => (old, new),
+        (Err(status), _) | (_, Err(status)) => return Ok(status)
+    };
+    let (old_zip, new_zip) = match (parse_archive(&old_archive.map, &options.old), parse_archive(&new_archive.map, &options.new)) {
+        (Ok(old), Ok(new)) => (old, new),
+        (Err(status), _) | (_, Err(status)) => return Ok(status)
+    };
+    let old_entries = diff::entries(&old_zip, &TextDecoder::new(options.charset, &old_zip)?)
+        .with_context(|| format!("{}: broken central directory", options.old))?;
+    let new_entries = diff::entries(&new_zip, &TextDecoder::new(options.charset, &new_zip)?)
+        .with_context(|| format!("{}: broken central directory", options.new))?;
+
+    let mut status = Status::Ok;
+    let mut differences = diff::compare(&old_entries, &new_entries);
+
+    if options.verify {
+        let unchanged: Vec<(&String, &CentralFileHeader, &CentralFileHeader)> = old_entries.iter()
+            .filter_map(|(name, old)| new_entries.get(name).map(|new| (name, old, new)))
+            .filter(|(_, old, new)| diff::Fingerprint::of(old) == diff::Fingerprint::of(new))
+            .collect();
+        let results: Vec<_> = unchanged.par_iter()
+            .map(|(name, old, new)| same_content(&old_zip, old, &new_zip, new, name))
+            .collect();
+
+        for ((name, old, new), result) in unchanged.iter().zip(results) {
+            match result {
+                Ok(true) => (),
+                Ok(false) => differences.push(diff::Difference {
+                    change: diff::Change::Modified,
+                    name: name.to_string(),
+                    old: Some(diff::Fingerprint::of(old)),
+                    new: Some(diff::Fingerprint::of(new)),
+                }),
+                Err(failure) => {
+                    eprintln!("unzipx: [{}] {}", failure.kind, failure);
+                    status = status.max(failure.kind.status());
+                }
+            }
+        }
+        differences.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+
+    let stdout = io::stdout();
+    let mut stdout = io::BufWriter::new(stdout.lock());
+    for difference in differences.iter() {
+        match options.format {
+            diff::Format::Text => writeln!(stdout, "{}  {}", difference.change.letter(), difference.name)?,
+            diff::Format::Json => {
+                serde_json::to_writer(&mut stdout, difference)?;
+                stdout.write_all(b"\n")?;
+            }
+        }
+    }
+    stdout.flush()?;
+    drop(stdout);
+
+    let count = |change| differences.iter().filter(|difference| difference.change == change).count();
+    eprintln!("unzipx: {} added, {} removed, {} modified",
+        count(diff::Change::Added),
+        count(diff::Change::Removed),
+        count(diff::Change::Modified)
+    );
+
+    if let Some(exdir) = options.extract {
+        status = status.max(extract_changed(&differences, &options.new, exdir, options.charset)?);
+    }
+
+    Ok(status)
+}
+
+/// extract the added and modified entries of the `new` archive into `exdir`.
+fn extract_changed(differences: &[diff::Difference], new: &Path, exdir: PathBuf, charset: Charset) -> anyhow::Result<Status> {
+    let changed: Vec<String> = differences.iter()
+        .filter(|difference| difference.change != diff::Change::Removed)
+        .map(|difference| difference.name.clone())
+        .collect();
+
+    // an empty filter selects everything
+    if changed.is_empty() {
+        return Ok(Status::Ok)
+    }
+    let mut filter = Filter::default();
+    filter.add_names(changed);
+    // modified entries replace the files of the older archive, the differences went to stdout
+    let config = Config {
+        charset,
+        keep_going: true,
+        overwrite: true,
+        quiet: true,
+        ..Config::new(exdir, filter, Limits::default())
+    };
+    unzip(&config, new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use testdata::Entry;
+
+    fn config(dir: &Path) -> Config {
+        Config::new(dir.to_path_buf(), Filter::default(), Limits::default())
+    }
+
+    /// what `-t` prints about an archive, without the header, and its status.
+    fn test_output(path: &Path) -> (String, Status) {
+        let config = Config {
+            keep_going: true,
+            ..config(path.parent().unwrap())
+        };
+        let mut out = String::new();
+        let status = test_archive(&config, path, &mut out).unwrap();
+        (out, status)
+    }
+
+    #[test]
+    fn archives_given_as_members() {
+        let (_dir, dir) = testdata::temp_dir();
+        let path = testdata::write_zip(&dir, "b.ZIP", &[]);
+
+        assert!(is_archive_file(path.as_str()));
+        assert!(!is_archive_file(dir.join("c.zip").as_str()));
+        assert!(!is_archive_file(dir.as_str()));
+        assert!(!is_archive_file("*.zip"));
+    }
+
+    #[test]
+    fn test_clean_archive() {
+        let (_dir, dir) = testdata::temp_dir();
+        let path = testdata::write_zip(&dir, "a.zip", &[
+            Entry::new("a.txt", "hello"),
+            Entry::new("b.txt", "stored").method(compress::STORE),
+            Entry::dir("c/"),
+        ]);
+
+        let (out, status) = test_output(&path);
+        assert_eq!(out, format!("No errors detected in compressed data of {}.\n", path));
+        assert_eq!(status as u8, 0);
+    }
+
+    #[test]
+    fn test_crc_mismatch() {
+        let (_dir, dir) = testdata::temp_dir();
+        let path = testdata::write_zip(&dir, "a.zip", &[
+            Entry::new("a.txt", "hello"),
+            Entry::new("b.txt", "hello").crc32(0x1234),
+            Entry::new("c.txt", "stored").method(compress::STORE).crc32(0x5678),
+        ]);
+
+        let (out, status) = test_output(&path);
+        let expect = [
+            format!("    testing: b.txt: crc32 check failed. expect: {}, got: {}", 0x1234, crc32fast::hash(b"hello")),
+            format!("    testing: c.txt: crc32 check failed. expect: {}, got: {}", 0x5678, crc32fast::hash(b"stored")),
+            format!("At least one error was detected in {}.", path),
+        ];
+        assert_eq!(out.lines().collect::<Vec<_>>(), expect);
+        assert_eq!(status, Status::Format);
+        assert_eq!(status as u8, 2);
+    }
+
+    #[test]
+    fn test_unsupported_method() {
+        let (_dir, dir) = testdata::temp_dir();
+        let path = testdata::write_zip(&dir, "a.zip", &[
+            // lzma
+            Entry::new("a.txt", "hello").method(14),
+            Entry::new("b.txt", "hello").crc32(0),
+        ]);
+
+        let (out, status) = test_output(&path);
+        assert!(out.contains("    testing: a.txt: unsupported compression method 14\n"), "{}", out);
+        assert!(out.contains("    testing: b.txt: crc32 check failed"), "{}", out);
+        // the worst problem decides
+        assert_eq!(status, Status::Unsupported);
+        assert_eq!(status as u8, 81);
+    }
+    #[test]
+    fn extract_stored() {
+        let (_dir, dir) = testdata::temp_dir();
+        let path = testdata::write_zip(&dir, "a.zip", &[
+            Entry::new("a.txt", "stored as it is").method(compress::STORE),
+            Entry::new("b.txt", "corrupted").method(compress::STORE).crc32(0x1234),
+        ]);
+        let archive = map_archive(&path).unwrap();
+        let zip = Zip64Archive::parse(&archive.map).unwrap();
+        let headers: Vec<_> = zip.entries().unwrap().map(Result::unwrap).collect();
+
+        for trust_stored in [false, true] {
+            let config = Config {
+                trust_stored,
+                ..config(&dir)
+            };
+            let out = dir.join(format!("trust-{}", trust_stored));
+            fs::create_dir(&out).unwrap();
+
+            let a = out.join("a.txt");
+            let part = manifest::part_path(&a);
+            write_stored(&config, &archive, &zip, &headers[0], "a.txt", &part).unwrap()
+                .persist(&a).unwrap();
+            assert_eq!(fs::read(&a).unwrap(), b"stored as it is");
+
+            let b = out.join("b.txt");
+            let part = manifest::part_path(&b);
+            let written = write_stored(&config, &archive, &zip, &headers[1], "b.txt", &part);
+            if trust_stored {
+                // copied without looking at the crc32
+                written.unwrap().persist(&b).unwrap();
+                assert_eq!(fs::read(&b).unwrap(), b"corrupted");
+            } else {
+                let failure = written.err().unwrap();
+                assert_eq!(failure.kind, Kind::Crc);
+                assert_eq!(failure.message, format!("crc32 check failed. expect: {}, got: {}", 0x1234, crc32fast::hash(b"corrupted")));
+                assert!(!part.exists());
+            }
+        }
+    }
+
+    /// the files under `dir` with their contents, `None` for directories.
+    fn tree(dir: &Path) -> BTreeMap<PathBuf, Option<Vec<u8>>> {
+        let mut files = BTreeMap::new();
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = PathBuf::from_path_buf(entry.unwrap().path()).unwrap();
+            if path.is_dir() {
+                files.extend(tree(&path));
+                files.insert(path, None);
+            } else {
+                let data = fs::read(&path).unwrap();
+                files.insert(path, Some(data));
+            }
+        }
+        files
+    }
+
+    #[test]
+    fn dry_run_matches_extraction() {
+        let (_dir, dir) = testdata::temp_dir();
+        let path = testdata::write_zip(&dir, "a.zip", &[
+            Entry::new("a.txt", "hello"),
+            Entry::dir("d/"),
+            Entry::new("d/b.txt", "in d"),
+            Entry::new("../up.txt", "sanitized"),
+            Entry::new("./a.txt", "same path"),
+            Entry::new("c", "file"),
+            Entry::new("c/e", "under a file"),
+            Entry::new("foreign.txt", "ours"),
+        ]);
+        let out = dir.join("out");
+        fs::create_dir(&out).unwrap();
+        fs::write(out.join("foreign.txt"), "theirs").unwrap();
+        let config = || Config {
+            keep_going: true,
+            quiet: true,
+            ..config(&out)
+        };
+
+        let before = tree(&dir);
+        let mut plan = Vec::new();
+        let planned = write_plan(&config(), &path, &mut plan).unwrap();
+        assert_eq!(tree(&dir), before);
+        assert!(!Manifest::path_of(&out, &path).exists());
+
+        let plan: Vec<serde_json::Value> = plan.split(|&b| b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect();
+        let summary: Vec<_> = plan.iter()
+            .map(|entry| (
+                entry["name"].as_str().unwrap(),
+                entry["path"].as_str().map(|path| Path::new(path).strip_prefix(&out).unwrap().as_str()),
+                entry["action"].as_str(),
+                entry["error"].as_str().map(|error| error.split(']').next().unwrap()),
+            ))
+            .collect();
+        assert_eq!(summary, [
+            ("a.txt", Some("a.txt"), Some("create"), None),
+            ("d/", Some("d"), Some("create"), None),
+            ("d/b.txt", Some("d/b.txt"), Some("create"), None),
+            ("../up.txt", Some("up.txt"), Some("create"), None),
+            ("./a.txt", None, None, Some("[collision")),
+            ("c", Some("c"), Some("create"), None),
+            ("c/e", None, None, Some("[collision")),
+            ("foreign.txt", Some("foreign.txt"), Some("exists"), None),
+        ]);
+
+        let extracted = unzip(&config(), &path).unwrap();
+        assert_eq!(extracted, planned);
+        // the foreign file can't be written
+        assert_eq!(extracted, Status::Format);
+
+        // what was planned to be created is all that was
+        let mut created: Vec<_> = plan.iter()
+            .filter(|entry| entry["action"] == "create")
+            .map(|entry| PathBuf::from(entry["path"].as_str().unwrap()))
+            .collect();
+        created.push(Manifest::path_of(&out, &path));
+        created.sort();
+        let after = tree(&dir);
+        let new: Vec<_> = after.keys().filter(|path| !before.contains_key(*path)).cloned().collect();
+        assert_eq!(new, created);
+        assert_eq!(after[&out.join("foreign.txt")].as_deref(), Some(&b"theirs"[..]));
+        assert_eq!(after[&out.join("a.txt")].as_deref(), Some(&b"hello"[..]));
+    }
+
+    #[test]
+    fn archives_of_a_run() {
+        let (_dir, dir) = testdata::temp_dir();
+        let archives = [
+            testdata::write_zip(&dir, "a.zip", &[Entry::new("a.txt", "hello").crc32(0), Entry::new("x.txt", "from a")]),
+            testdata::write_zip(&dir, "b.zip", &[Entry::new("b.txt", "hello"), Entry::new("x.txt", "from b")]),
+        ];
+
+        // the statuses and the summary of a run, and what it extracted
+        let run = |fail_fast: bool| {
+            let out = dir.join(format!("fail-fast-{}", fail_fast));
+            let config = Config {
+                keep_going: true,
+                quiet: true,
+                fail_fast,
+                progress: Progress::new(2),
+                ..config(&out)
+            };
+            let statuses = run_archives(&config, &archives, unzip, false);
+            let mut summary = Vec::new();
+            config.tally.write(&mut summary, &statuses).unwrap();
+
+            let statuses: Vec<_> = statuses.into_iter().map(|(_, status)| status).collect();
+            (statuses, String::from_utf8(summary).unwrap(), out)
+        };
+
+        let (statuses, summary, out) = run(true);
+        assert_eq!(statuses, [Some(Status::Format), None]);
+        assert_eq!(summary, format!(
+            "unzipx: 2 archives, 0 ok, 1 with problems, 1 skipped; 2 entries processed, 1 error(s)\n\
+            unzipx:   {}: errors\n\
+            unzipx:   {}: skipped\n",
+            archives[0],
+            archives[1]
+        ));
+        assert!(!out.join("b.txt").exists());
+
+        let (statuses, summary, out) = run(false);
+        // x.txt of b.zip collides with the one of a.zip
+        assert_eq!(statuses, [Some(Status::Format), Some(Status::Warning)]);
+        assert_eq!(summary.lines().next().unwrap(), "unzipx: 2 archives, 0 ok, 2 with problems, 0 skipped; 4 entries processed, 2 error(s)");
+        assert_eq!(fs::read_to_string(out.join("x.txt")).unwrap(), "from a");
+        assert_eq!(fs::read_to_string(out.join("b.txt")).unwrap(), "hello");
+    }
+
+    #[test]
+    fn extract_changed_entries() {
+        let (_dir, dir) = testdata::temp_dir();
+        let old = testdata::write_zip(&dir, "old.zip", &[
+            Entry::new("same.txt", "same"),
+            Entry::new("removed.txt", "removed"),
+            Entry::new("modified.txt", "old"),
+        ]);
+        let new = testdata::write_zip(&dir, "new.zip", &[
+            Entry::new("same.txt", "same"),
+            Entry::new("modified.txt", "new"),
+            Entry::new("added.txt", "added"),
+        ]);
+        let out = dir.join("out");
+        assert_eq!(unzip(&config(&out), &old).unwrap(), Status::Ok);
+        fs::remove_file(out.join("same.txt")).unwrap();
+
+        let old_archive = map_archive(&old).unwrap();
+        let old_zip = Zip64Archive::parse(&old_archive.map).unwrap();
+        let new_archive = map_archive(&new).unwrap();
+        let new_zip = Zip64Archive::parse(&new_archive.map).unwrap();
+        let differences = diff::compare(
+            &diff::entries(&old_zip, &TextDecoder::new(Charset::Auto, &old_zip).unwrap()).unwrap(),
+            &diff::entries(&new_zip, &TextDecoder::new(Charset::Auto, &new_zip).unwrap()).unwrap()
+        );
+
+        assert_eq!(extract_changed(&differences, &new, out.clone(), Charset::Auto).unwrap(), Status::Ok);
+        assert_eq!(fs::read_to_string(out.join("added.txt")).unwrap(), "added");
+        assert_eq!(fs::read_to_string(out.join("modified.txt")).unwrap(), "new");
+        // left alone
+        assert_eq!(fs::read_to_string(out.join("removed.txt")).unwrap(), "removed");
+        assert!(!out.join("same.txt").exists());
+
+        // nothing changed, nothing is extracted
+        let empty = dir.join("empty");
+        assert_eq!(extract_changed(&[], &new, empty.clone(), Charset::Auto).unwrap(), Status::Ok);
+        assert!(!empty.exists());
+    }
+
+    #[test]
+    fn first_colliding_entry_is_extracted() {
+        let (_dir, dir) = testdata::temp_dir();
+        let mut entries = vec![
+            Entry::new("x", "first"),
+            Entry::new("a", "file"),
+            Entry::new("c/d", "file"),
+        ];
+        // enough entries for the workers to run out of order
+        entries.extend((0..64).map(|i| Entry::new(format!("f/{}", i), vec![b'.'; 4096])));
+        entries.extend([
+            Entry::new("./x", "second"),
+            Entry::new("a/b", "under a file"),
+            Entry::new("c", "over a directory"),
+        ]);
+        let path = testdata::write_zip(&dir, "a.zip", &entries);
+
+        let out = dir.join("out");
+        let config = Config {
+            keep_going: true,
+            quiet: true,
+            ..config(&out)
+        };
+        assert_eq!(unzip(&config, &path).unwrap(), Status::Warning);
+        assert_eq!(fs::read_to_string(out.join("x")).unwrap(), "first");
+        assert_eq!(fs::read_to_string(out.join("a")).unwrap(), "file");
+        assert_eq!(fs::read_to_string(out.join("c/d")).unwrap(), "file");
+        assert!(out.join("c").is_dir());
+    }
+}
@@ -0,0 +1,262 @@
+use std::{ fs, io };
+use std::io::{ BufRead, Read, Write };
+use std::sync::Mutex;
+use std::sync::atomic::{ AtomicUsize, Ordering };
+use std::collections::HashMap;
+use anyhow::Context;
+use camino::{ Utf8Path as Path, Utf8PathBuf as PathBuf };
+use serde::{ Deserialize, Serialize };
+use zip_parser::CentralFileHeader;
+use crate::sanitize::Destination;
+
+
+/// An entry that was extracted completely, one JSON line of the manifest.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Record {
+    pub name: String,
+    pub size: u64,
+    pub crc32: u32,
+}
+
+/// What extraction does about the destination of an entry.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    /// nothing is there yet.
+    Create,
+    /// the file already holds the entry, a previous run extracted it, see `--resume`.
+    Skip,
+    /// a previous run extracted another version of the entry, or overwriting was asked for, replace it.
+    Rewrite,
+    /// something that isn't ours is there, refuse to touch it.
+    Exists,
+}
+
+/// file names ending in these are ours, entries can't be extracted to them.
+pub const RESERVED_SUFFIXES: [&str; 2] = [PART_SUFFIX, MANIFEST_SUFFIX];
+const PART_SUFFIX: &str = ".unzipx-part";
+const MANIFEST_SUFFIX: &str = ".unzipx-manifest";
+
+/// where an entry is written before it's renamed to `dest`.
+pub fn part_path(dest: &Path) -> PathBuf {
+    format!("{}{}", dest, PART_SUFFIX).into()
+}
+
+/// Sidecar file in the extraction directory listing the entries of one archive
+/// that were extracted completely.
+///
+/// entries are written to their `part_path`, renamed into place and then recorded,
+/// so a file at the destination is complete, and ours if it has a record. a crash between
+/// the rename and the record leaves a file without one, resuming recognizes it by its crc32.
+/// records are never removed, a later record of the same name replaces an earlier one.
+pub struct Manifest {
+    pub path: PathBuf,
+    resume: bool,
+    /// records of previous runs, when resuming.
+    completed: HashMap<String, Record>,
+    /// replace files that aren't ours too.
+    overwrite: bool,
+    /// `None` when the manifest is only inspected.
+    file: Option<Mutex<fs::File>>,
+    skipped: AtomicUsize,
+}
+
+impl Manifest {
+    /// where the manifest of `archive` goes in `dir`.
+    pub fn path_of(dir: &Path, archive: &Path) -> PathBuf {
+        dir.join(format!(".{}{}", archive.file_name().unwrap_or("archive"), MANIFEST_SUFFIX))
+    }
+
+    /// open the manifest for appending, and read the records of previous runs if `resume` is set.
+    pub fn open(dir: &Path, archive: &Path, resume: bool, overwrite: bool) -> anyhow::Result<Manifest> {
+        let path = Manifest::path_of(dir, archive);
+        let completed = if resume { Manifest::load(&path)? } else { HashMap::new() };
+
+        fs::create_dir_all(dir).with_context(|| format!("create {}", dir))?;
+        let file = fs::File::options()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("open manifest {}", path))?;
+
+        Ok(Manifest {
+            path,
+            resume,
+            completed,
+            overwrite,
+            file: Some(Mutex::new(file)),
+            skipped: AtomicUsize::new(0),
+        })
+    }
+
+    /// like `open`, but leave the filesystem alone and record nothing, for planning.
+    pub fn inspect(dir: &Path, archive: &Path, resume: bool, overwrite: bool) -> anyhow::Result<Manifest> {
+        let path = Manifest::path_of(dir, archive);
+        let completed = if resume { Manifest::load(&path)? } else { HashMap::new() };
+
+        Ok(Manifest {
+            path,
+            resume,
+            completed,
+            overwrite,
+            file: None,
+            skipped: AtomicUsize::new(0),
+        })
+    }
+
+    /// read the records of previous runs, a missing manifest has none.
+    pub fn load(path: &Path) -> anyhow::Result<HashMap<String, Record>> {
+        let file = match fs::File::open(path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(HashMap::new()),
+            Err(err) => return Err(err).with_context(|| format!("open manifest {}", path))
+        };
+
+        let mut completed = HashMap::new();
+        for line in io::BufReader::new(file).lines() {
+            let line = line.with_context(|| format!("read manifest {}", path))?;
+            // the last line may have been cut off by a crash
+            if let Ok(record) = serde_json::from_str::<Record>(&line) {
+                completed.insert(record.name.clone(), record);
+            }
+        }
+        Ok(completed)
+    }
+
+    /// decide what to do about the destination of an entry, without touching it.
+    pub fn plan(&self, cfh: &CentralFileHeader, name: &str, dest: &Destination) -> Action {
+        if dest.is_dir {
+            return Action::Create
+        }
+        let metadata = match fs::symlink_metadata(&dest.path) {
+            Ok(metadata) => metadata,
+            Err(_) => return Action::Create
+        };
+        if !metadata.is_file() {
+            return Action::Exists
+        }
+
+        // what the file holds decides, the record may be of another version of the entry,
+        // or missing after a crash
+        let complete = self.resume
+            && metadata.len() == u64::from(cfh.uncomp_size)
+            && file_crc32(&dest.path).is_ok_and(|crc32| crc32 == cfh.crc32);
+        if complete {
+            return Action::Skip
+        }
+        // there are records only when resuming
+        if self.completed.contains_key(name) || self.overwrite {
+            Action::Rewrite
+        } else {
+            // not written by a previous run
+            Action::Exists
+        }
+    }
+
+    /// count an entry that was skipped because it's complete.
+    pub fn skip(&self) {
+        self.skipped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn skipped(&self) -> usize {
+        self.skipped.load(Ordering::Relaxed)
+    }
+
+    /// note an entry as extracted completely, once its file is in place.
+    pub fn record(&self, cfh: &CentralFileHeader, name: &str) -> io::Result<()> {
+        let record = Record {
+            name: name.into(),
+            size: cfh.uncomp_size.into(),
+            crc32: cfh.crc32,
+        };
+        let mut line = serde_json::to_vec(&record)?;
+        line.push(b'\n');
+
+        // a single write per record keeps concurrent records apart
+        match &self.file {
+            Some(file) => file.lock().unwrap().write_all(&line),
+            None => Ok(())
+        }
+    }
+}
+
+fn file_crc32(path: &Path) -> io::Result<u32> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = crc32fast::Hasher::new();
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        match file.read(&mut buf) {
+            Ok(0) => return Ok(hasher.finalize()),
+            Ok(n) => hasher.update(&buf[..n]),
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+            Err(err) => return Err(err)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zip_parser::Zip64Archive;
+    use crate::testdata::{ self, Entry };
+
+    fn plan(manifest: &Manifest, cfh: &CentralFileHeader, dest: &Path) -> Action {
+        let dest = Destination { path: dest.to_path_buf(), is_dir: false, issues: Vec::new() };
+        manifest.plan(cfh, "a.txt", &dest)
+    }
+
+    #[test]
+    fn plans() {
+        let (_dir, dir) = testdata::temp_dir();
+        let buf = testdata::zip(&[Entry::new("a.txt", "hello")]);
+        let zip = Zip64Archive::parse(&buf).unwrap();
+        let cfh = zip.entries().unwrap().next().unwrap().unwrap();
+        let archive = Path::new("a.zip");
+        let dest = dir.join("a.txt");
+
+        Manifest::open(&dir, archive, false, false).unwrap().record(&cfh, "a.txt").unwrap();
+        let resumed = Manifest::open(&dir, archive, true, false).unwrap();
+        let fresh = Manifest::open(&dir, archive, false, false).unwrap();
+
+        // recorded, but the file is gone
+        assert_eq!(plan(&resumed, &cfh, &dest), Action::Create);
+
+        fs::write(&dest, "hello").unwrap();
+        assert_eq!(plan(&resumed, &cfh, &dest), Action::Skip);
+        // without resuming it isn't ours
+        assert_eq!(plan(&fresh, &cfh, &dest), Action::Exists);
+
+        // cut off
+        fs::write(&dest, "hel").unwrap();
+        assert_eq!(plan(&resumed, &cfh, &dest), Action::Rewrite);
+        // same size, other content
+        fs::write(&dest, "jello").unwrap();
+        assert_eq!(plan(&resumed, &cfh, &dest), Action::Rewrite);
+    }
+
+    #[test]
+    fn plans_without_record() {
+        let (_dir, dir) = testdata::temp_dir();
+        let buf = testdata::zip(&[Entry::new("a.txt", "hello")]);
+        let zip = Zip64Archive::parse(&buf).unwrap();
+        let cfh = zip.entries().unwrap().next().unwrap().unwrap();
+        let archive = Path::new("a.zip");
+        let dest = dir.join("a.txt");
+
+        let resumed = Manifest::open(&dir, archive, true, false).unwrap();
+        let overwrite = Manifest::open(&dir, archive, false, true).unwrap();
+
+        // renamed into place, then the run crashed before recording it
+        fs::write(&dest, "hello").unwrap();
+        assert_eq!(plan(&resumed, &cfh, &dest), Action::Skip);
+
+        // somebody else's
+        fs::write(&dest, "other").unwrap();
+        assert_eq!(plan(&resumed, &cfh, &dest), Action::Exists);
+        assert_eq!(plan(&overwrite, &cfh, &dest), Action::Rewrite);
+
+        fs::remove_file(&dest).unwrap();
+        fs::create_dir(&dest).unwrap();
+        assert_eq!(plan(&overwrite, &cfh, &dest), Action::Exists);
+    }
+}
@@ -0,0 +1,123 @@
+use std::sync::{ Mutex, Condvar };
+use std::collections::BTreeMap;
+
+
+/// Hands results of parallel workers out in index order.
+///
+/// Workers may only start on an index less than `window` ahead of the oldest
+/// result not handed out yet, which bounds the results held back.
+/// Every index must be pushed, skipped ones included, or the later ones wait forever.
+pub struct Reorder<T> {
+    window: usize,
+    state: Mutex<State<T>>,
+    turn: Condvar,
+}
+
+struct State<T> {
+    next: usize,
+    pending: BTreeMap<usize, T>,
+}
+
+impl<T> Reorder<T> {
+    pub fn new(window: usize) -> Reorder<T> {
+        Reorder {
+            window: window.max(1),
+            state: Mutex::new(State { next: 0, pending: BTreeMap::new() }),
+            turn: Condvar::new(),
+        }
+    }
+
+    /// block until `index` is inside the window.
+    pub fn wait_turn(&self, index: usize) {
+        let state = self.state.lock().unwrap();
+        let _state = self.turn
+            .wait_while(state, |state| index >= state.next + self.window)
+            .unwrap();
+    }
+
+    /// hand in the result for `index`, and `emit` every result that is next in order.
+    ///
+    /// `emit` runs with the buffer locked, so emitted results never interleave.
+    pub fn push(&self, index: usize, value: T, mut emit: impl FnMut(T)) {
+        let mut state = self.state.lock().unwrap();
+        state.pending.insert(index, value);
+
+        let mut advanced = false;
+        loop {
+            let next = state.next;
+            match state.pending.remove(&next) {
+                Some(value) => emit(value),
+                None => break
+            }
+            state.next += 1;
+            advanced = true;
+        }
+
+        if advanced {
+            self.turn.notify_all();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn emits_in_index_order() {
+        let reorder = Reorder::new(8);
+        let mut out = Vec::new();
+
+        reorder.push(2, 'c', |value| out.push(value));
+        reorder.push(1, 'b', |value| out.push(value));
+        assert!(out.is_empty());
+        reorder.push(0, 'a', |value| out.push(value));
+        assert_eq!(out, ['a', 'b', 'c']);
+        reorder.push(3, 'd', |value| out.push(value));
+        assert_eq!(out, ['a', 'b', 'c', 'd']);
+    }
+
+    #[test]
+    fn window_holds_workers_back() {
+        let reorder = Arc::new(Reorder::new(2));
+        reorder.wait_turn(0);
+        reorder.wait_turn(1);
+
+        let waiting = {
+            let reorder = reorder.clone();
+            thread::spawn(move || {
+                reorder.wait_turn(3);
+                reorder.push(3, 3, |_| ());
+            })
+        };
+        let mut out = Vec::new();
+        reorder.push(1, 1, |value| out.push(value));
+        reorder.push(0, 0, |value| out.push(value));
+        assert_eq!(out, [0, 1]);
+
+        // 3 is inside the window now, but waits for 2 to be handed out
+        reorder.wait_turn(2);
+        waiting.join().unwrap();
+        reorder.push(2, 2, |value| out.push(value));
+        assert_eq!(out, [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn parallel_workers() {
+        let reorder = Reorder::new(4);
+        let out = Mutex::new(Vec::new());
+
+        thread::scope(|scope| {
+            for worker in 0..4 {
+                let (reorder, out) = (&reorder, &out);
+                scope.spawn(move || for index in (worker..100).step_by(4) {
+                    reorder.wait_turn(index);
+                    reorder.push(index, index, |value| out.lock().unwrap().push(value));
+                });
+            }
+        });
+        assert_eq!(out.into_inner().unwrap(), (0..100).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,266 @@
+use std::io::{ self, Write };
+use std::str::FromStr;
+use std::sync::mpsc;
+use std::sync::atomic::{ AtomicU64, Ordering };
+use std::time::{ Duration, Instant };
+use std::thread;
+use serde::Serialize;
+
+
+/// How progress is printed to stderr.
+#[derive(Clone, Copy, Debug)]
+pub enum ProgressMode {
+    /// a single status line, redrawn in place.
+    Bar,
+    /// one JSON object per line.
+    Json,
+}
+
+impl ProgressMode {
+    fn interval(self) -> Duration {
+        match self {
+            ProgressMode::Bar => Duration::from_millis(200),
+            ProgressMode::Json => Duration::from_secs(1),
+        }
+    }
+}
+
+impl FromStr for ProgressMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ProgressMode, String> {
+        match s {
+            "bar" => Ok(ProgressMode::Bar),
+            "json" => Ok(ProgressMode::Json),
+            _ => Err(format!("unknown progress mode `{}`, expected one of: bar, json", s))
+        }
+    }
+}
+
+/// Counters shared by the workers of a run, across its archives.
+///
+/// the totals grow as archives are opened.
+pub struct Progress {
+    total_archives: u64,
+    archives: AtomicU64,
+    total_entries: AtomicU64,
+    total_bytes: AtomicU64,
+    entries: AtomicU64,
+    comp_bytes: AtomicU64,
+    uncomp_bytes: AtomicU64,
+    start: Instant,
+}
+
+#[derive(Serialize)]
+struct Event<'a> {
+    /// the archive, or the wildcard selecting the archives of the run.
+    archive: &'a str,
+    done: bool,
+    /// archives opened so far.
+    archives: u64,
+    total_archives: u64,
+    entries: u64,
+    total_entries: u64,
+    compressed_bytes: u64,
+    total_compressed_bytes: u64,
+    uncompressed_bytes: u64,
+    elapsed_secs: f64,
+    bytes_per_sec: f64,
+    eta_secs: Option<f64>,
+}
+
+impl Progress {
+    pub fn new(total_archives: u64) -> Progress {
+        Progress {
+            total_archives,
+            archives: AtomicU64::new(0),
+            total_entries: AtomicU64::new(0),
+            total_bytes: AtomicU64::new(0),
+            entries: AtomicU64::new(0),
+            comp_bytes: AtomicU64::new(0),
+            uncomp_bytes: AtomicU64::new(0),
+            start: Instant::now(),
+        }
+    }
+
+    /// count an archive as opened, `total_bytes` is the size of its compressed data,
+    /// usually the offset of the central directory.
+    pub fn add_archive(&self, total_entries: u64, total_bytes: u64) {
+        self.archives.fetch_add(1, Ordering::Relaxed);
+        self.total_entries.fetch_add(total_entries, Ordering::Relaxed);
+        self.total_bytes.fetch_add(total_bytes, Ordering::Relaxed);
+    }
+
+    /// count an entry as done, skipped entries count with an `uncomp_size` of zero.
+    pub fn add(&self, comp_size: u64, uncomp_size: u64) {
+        self.entries.fetch_add(1, Ordering::Relaxed);
+        self.comp_bytes.fetch_add(comp_size, Ordering::Relaxed);
+        self.uncomp_bytes.fetch_add(uncomp_size, Ordering::Relaxed);
+    }
+
+    /// run `f` while another thread prints the progress of `archive` every interval.
+    ///
+    /// there is a single watch for the run, archives processed at once share its line.
+    pub fn watch<T>(&self, mode: Option<ProgressMode>, archive: &str, f: impl FnOnce() -> T) -> T {
+        let mode = match mode {
+            Some(mode) => mode,
+            None => return f()
+        };
+
+        let (tx, rx) = mpsc::channel::<()>();
+        thread::scope(|s| {
+            s.spawn(move || loop {
+                match rx.recv_timeout(mode.interval()) {
+                    Err(mpsc::RecvTimeoutError::Timeout) => self.print(mode, archive, false),
+                    _ => break self.print(mode, archive, true)
+                }
+            });
+
+            let output = f();
+            drop(tx);
+            output
+        })
+    }
+
+    fn event<'a>(&self, archive: &'a str, done: bool) -> Event<'a> {
+        let entries = self.entries.load(Ordering::Relaxed);
+        let comp_bytes = self.comp_bytes.load(Ordering::Relaxed);
+        let uncomp_bytes = self.uncomp_bytes.load(Ordering::Relaxed);
+        let total_bytes = self.total_bytes.load(Ordering::Relaxed);
+        let elapsed = self.start.elapsed().as_secs_f64();
+
+        let bytes_per_sec = if elapsed > 0.0 { comp_bytes as f64 / elapsed } else { 0.0 };
+        let eta_secs = if done {
+            Some(0.0)
+        } else if bytes_per_sec > 0.0 {
+            Some(total_bytes.saturating_sub(comp_bytes) as f64 / bytes_per_sec)
+        } else {
+            None
+        };
+
+        Event {
+            archive,
+            done,
+            archives: self.archives.load(Ordering::Relaxed),
+            total_archives: self.total_archives,
+            entries,
+            total_entries: self.total_entries.load(Ordering::Relaxed),
+            compressed_bytes: comp_bytes,
+            total_compressed_bytes: total_bytes,
+            uncompressed_bytes: uncomp_bytes,
+            elapsed_secs: elapsed,
+            bytes_per_sec,
+            eta_secs,
+        }
+    }
+
+    fn print(&self, mode: ProgressMode, archive: &str, done: bool) {
+        let event = self.event(archive, done);
+        let mut stderr = io::stderr().lock();
+
+        // progress is best effort, a closed stderr shouldn't stop the work
+        let _ = match mode {
+            ProgressMode::Bar => {
+                let eta = match event.eta_secs {
+                    Some(secs) => format!("{}s", secs.ceil()),
+                    None => "-".into()
+                };
+                let archives = if event.total_archives > 1 {
+                    format!("{}/{} archives, ", event.archives, event.total_archives)
+                } else {
+                    String::new()
+                };
+                write!(stderr, "\r\x1b[2K{}: {}{}/{} entries, {} / {} compressed, {} uncompressed, {}/s, eta {}{}",
+                    archive,
+                    archives,
+                    event.entries,
+                    event.total_entries,
+                    human_bytes(event.compressed_bytes),
+                    human_bytes(event.total_compressed_bytes),
+                    human_bytes(event.uncompressed_bytes),
+                    human_bytes(event.bytes_per_sec as u64),
+                    eta,
+                    if done { "\n" } else { "" }
+                )
+            },
+            ProgressMode::Json => {
+                serde_json::to_writer(&mut stderr, &event)
+                    .map_err(io::Error::from)
+                    .and_then(|_| stderr.write_all(b"\n"))
+            }
+        };
+    }
+}
+
+fn human_bytes(n: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+
+    let mut n = n as f64;
+    let mut unit = 0;
+    while n >= 1024.0 && unit < UNITS.len() - 1 {
+        n /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", n, UNITS[unit])
+    } else {
+        format!("{:.1} {}", n, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_event() {
+        let progress = Progress::new(3);
+        progress.add_archive(3, 600);
+        progress.add(300, 900);
+        // a skipped entry
+        progress.add(200, 0);
+        progress.add_archive(1, 400);
+
+        let line = serde_json::to_string(&progress.event("data/*.zip", false)).unwrap();
+        let event: serde_json::Map<String, serde_json::Value> = serde_json::from_str(&line).unwrap();
+        let keys: Vec<_> = event.keys().map(String::as_str).collect();
+        assert_eq!(keys, [
+            "archive",
+            "archives",
+            "bytes_per_sec",
+            "compressed_bytes",
+            "done",
+            "elapsed_secs",
+            "entries",
+            "eta_secs",
+            "total_archives",
+            "total_compressed_bytes",
+            "total_entries",
+            "uncompressed_bytes",
+        ]);
+        assert_eq!(event["archive"], "data/*.zip");
+        assert_eq!(event["done"], false);
+        assert_eq!(event["archives"], 2);
+        assert_eq!(event["total_archives"], 3);
+        assert_eq!(event["entries"], 2);
+        assert_eq!(event["total_entries"], 4);
+        assert_eq!(event["compressed_bytes"], 500);
+        assert_eq!(event["total_compressed_bytes"], 1000);
+        assert_eq!(event["uncompressed_bytes"], 900);
+        assert!(event["elapsed_secs"].as_f64().unwrap() >= 0.0);
+
+        let done = serde_json::to_value(progress.event("data/*.zip", true)).unwrap();
+        assert_eq!(done["done"], true);
+        assert_eq!(done["eta_secs"], 0.0);
+    }
+
+    #[test]
+    fn bytes_for_humans() {
+        assert_eq!(human_bytes(0), "0 B");
+        assert_eq!(human_bytes(1023), "1023 B");
+        assert_eq!(human_bytes(1536), "1.5 KiB");
+        assert_eq!(human_bytes(5 << 40), "5.0 TiB");
+        assert_eq!(human_bytes(5 << 50), "5120.0 TiB");
+    }
+}
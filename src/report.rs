@@ -0,0 +1,319 @@
+use std::{ fmt, io };
+use std::io::Write;
+use std::process::ExitCode;
+use std::collections::BTreeMap;
+use std::sync::atomic::{ AtomicUsize, Ordering };
+use camino::Utf8Path as Path;
+use crate::util::Crc32Mismatch;
+
+
+/// Exit status, compatible with Info-ZIP unzip. see `unzip(1)` DIAGNOSTICS.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Status {
+    /// normal; no errors or warnings detected.
+    Ok = 0,
+    /// one or more warning errors were encountered, but processing completed successfully anyway.
+    Warning = 1,
+    /// a generic error in the zipfile format was detected.
+    Format = 2,
+    /// a severe error in the zipfile format was detected.
+    Severe = 3,
+    /// no zipfiles were found.
+    NotFound = 9,
+    /// no matching files were found.
+    NoMatch = 11,
+    /// unsupported compression methods.
+    Unsupported = 81,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Status::Ok => "ok",
+            Status::Warning => "warnings",
+            Status::Format => "errors",
+            Status::Severe => "severe errors",
+            Status::NotFound => "not found",
+            Status::NoMatch => "no match",
+            Status::Unsupported => "unsupported methods",
+        })
+    }
+}
+
+impl From<Status> for ExitCode {
+    fn from(status: Status) -> ExitCode {
+        ExitCode::from(status as u8)
+    }
+}
+
+/// What went wrong with an entry.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Kind {
+    /// the central directory entry couldn't be parsed.
+    CentralDirectory,
+    /// the local header or the compressed data couldn't be located.
+    LocalHeader,
+    /// the compression method isn't supported.
+    Unsupported,
+    /// the decompressor couldn't be set up.
+    Decoder,
+    /// the compressed data is corrupt.
+    Decompress,
+    /// the decompressed data doesn't match its crc32.
+    Crc,
+    /// the decompressed data isn't a valid SEC submissions file.
+    Parse,
+    /// the selected entries exceed `--max-entries`.
+    TooManyEntries,
+    /// the entry exceeds `--max-entry-size`.
+    EntryTooLarge,
+    /// the entries exceed `--max-total-size`.
+    TotalTooLarge,
+    /// the entry exceeds `--max-ratio`.
+    Ratio,
+    /// the entry name isn't a safe relative path, see `--path-policy`.
+    UnsafePath,
+    /// the entry has the same output path as another entry.
+    Collision,
+    /// the entry couldn't be written out.
+    Write,
+}
+
+impl Kind {
+    pub fn status(self) -> Status {
+        match self {
+            Kind::CentralDirectory => Status::Severe,
+            Kind::LocalHeader | Kind::Decoder | Kind::Decompress | Kind::Crc => Status::Format,
+            Kind::Unsupported => Status::Unsupported,
+            Kind::Parse => Status::Warning,
+            Kind::TooManyEntries | Kind::EntryTooLarge | Kind::TotalTooLarge | Kind::Ratio => Status::Severe,
+            Kind::UnsafePath | Kind::Collision => Status::Warning,
+            // unzip doesn't tell write errors apart either
+            Kind::Write => Status::Format,
+        }
+    }
+
+    /// classify an error returned while reading an entry.
+    pub fn of_read(err: &io::Error) -> Kind {
+        match err.get_ref() {
+            Some(inner) if inner.is::<Crc32Mismatch>() => Kind::Crc,
+            _ => Kind::Decompress
+        }
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Kind::CentralDirectory => "central directory",
+            Kind::LocalHeader => "local header",
+            Kind::Unsupported => "unsupported method",
+            Kind::Decoder => "decoder",
+            Kind::Decompress => "decompress",
+            Kind::Crc => "crc32",
+            Kind::Parse => "parse",
+            Kind::TooManyEntries => "max entries",
+            Kind::EntryTooLarge => "max entry size",
+            Kind::TotalTooLarge => "max total size",
+            Kind::Ratio => "max ratio",
+            Kind::UnsafePath => "unsafe path",
+            Kind::Collision => "collision",
+            Kind::Write => "write",
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Failure {
+    pub name: String,
+    pub kind: Kind,
+    pub message: String,
+}
+
+impl Failure {
+    pub fn new(name: &str, kind: Kind, message: impl fmt::Display) -> Failure {
+        Failure {
+            name: name.into(),
+            kind,
+            message: message.to_string()
+        }
+    }
+
+    pub fn read(name: &str, err: io::Error) -> Failure {
+        Failure::new(name, Kind::of_read(&err), err)
+    }
+
+    /// `path` couldn't be written while extracting `name`.
+    pub fn write(name: &str, path: &Path, err: io::Error) -> Failure {
+        Failure::new(name, Kind::Write, format_args!("{}: {}", path, err))
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.name, self.message)
+    }
+}
+
+impl std::error::Error for Failure {}
+
+/// Outcome of processing the entries of one archive.
+#[derive(Default, Debug)]
+pub struct Report {
+    /// entries that were selected and processed, successfully or not.
+    pub processed: usize,
+    pub failures: Vec<Failure>,
+    /// processing stopped at the first failure, remaining entries were skipped.
+    pub stopped: bool,
+    /// failures were already printed as they happened.
+    pub printed: bool,
+}
+
+impl Report {
+    pub fn status(&self) -> Status {
+        self.failures.iter()
+            .map(|failure| failure.kind.status())
+            .max()
+            .unwrap_or(Status::Ok)
+    }
+
+    pub fn counts(&self) -> BTreeMap<Kind, usize> {
+        let mut counts = BTreeMap::new();
+        for failure in self.failures.iter() {
+            *counts.entry(failure.kind).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// sort failures by entry name, parallel workers finish in any order.
+    pub fn sort(&mut self) {
+        self.failures.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+
+    /// print every failure and a count by kind to stderr, in one piece.
+    pub fn eprint(&self, path: impl fmt::Display) {
+        if self.failures.is_empty() {
+            return
+        }
+
+        // other archives may be reporting at the same time
+        let mut stderr = io::stderr().lock();
+
+        // best effort, like eprintln
+        if !self.printed {
+            for failure in self.failures.iter() {
+                let _ = writeln!(stderr, "unzipx: [{}] {}", failure.kind, failure);
+            }
+        }
+
+        let counts = self.counts()
+            .into_iter()
+            .map(|(kind, count)| format!("{} {}", count, kind))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let _ = writeln!(stderr, "unzipx: {}: {} error(s) in {} entries processed ({})",
+            path,
+            self.failures.len(),
+            self.processed,
+            counts
+        );
+
+        if self.stopped {
+            let _ = writeln!(stderr, "unzipx: stopped at the first error, use --keep-going to process the remaining entries");
+        }
+    }
+}
+
+/// Entry counts across the archives of a run.
+#[derive(Default, Debug)]
+pub struct Tally {
+    processed: AtomicUsize,
+    failed: AtomicUsize,
+}
+
+impl Tally {
+    pub fn add(&self, processed: usize, failed: usize) {
+        self.processed.fetch_add(processed, Ordering::Relaxed);
+        self.failed.fetch_add(failed, Ordering::Relaxed);
+    }
+
+    /// write the totals and the status of every archive, `None` for skipped ones.
+    pub fn write<P: fmt::Display>(&self, out: &mut impl Write, archives: &[(P, Option<Status>)]) -> io::Result<()> {
+        let ok = archives.iter()
+            .filter(|(_, status)| *status == Some(Status::Ok))
+            .count();
+        let skipped = archives.iter()
+            .filter(|(_, status)| status.is_none())
+            .count();
+        writeln!(out, "unzipx: {} archives, {} ok, {} with problems, {} skipped; {} entries processed, {} error(s)",
+            archives.len(),
+            ok,
+            archives.len() - ok - skipped,
+            skipped,
+            self.processed.load(Ordering::Relaxed),
+            self.failed.load(Ordering::Relaxed)
+        )?;
+
+        for (path, status) in archives.iter() {
+            match status {
+                Some(status) => writeln!(out, "unzipx:   {}: {}", path, status)?,
+                None => writeln!(out, "unzipx:   {}: skipped", path)?
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(failures: &[(&str, Kind)]) -> Report {
+        Report {
+            processed: 10,
+            failures: failures.iter().map(|&(name, kind)| Failure::new(name, kind, "broken")).collect(),
+            ..Report::default()
+        }
+    }
+
+    #[test]
+    fn counts_by_kind() {
+        let report = report(&[("c", Kind::Crc), ("a", Kind::Write), ("b", Kind::Crc), ("d", Kind::Collision)]);
+        let counts: Vec<_> = report.counts().into_iter().collect();
+        assert_eq!(counts, [(Kind::Crc, 2), (Kind::Collision, 1), (Kind::Write, 1)]);
+    }
+
+    #[test]
+    fn worst_kind_decides_the_status() {
+        assert_eq!(report(&[]).status(), Status::Ok);
+        assert_eq!(report(&[("a", Kind::Collision)]).status(), Status::Warning);
+        assert_eq!(report(&[("a", Kind::Collision), ("b", Kind::Crc)]).status(), Status::Format);
+        assert_eq!(report(&[("a", Kind::Unsupported), ("b", Kind::CentralDirectory)]).status(), Status::Unsupported);
+        assert_eq!(report(&[("a", Kind::Ratio), ("b", Kind::Write)]).status(), Status::Severe);
+    }
+
+    #[test]
+    fn failures_sort_by_name() {
+        let mut report = report(&[("c", Kind::Crc), ("a", Kind::Write), ("b", Kind::Crc)]);
+        report.sort();
+        let names: Vec<_> = report.failures.iter().map(|failure| failure.name.as_str()).collect();
+        assert_eq!(names, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn read_errors_are_classified() {
+        let crc = io::Error::new(io::ErrorKind::InvalidData, Crc32Mismatch { expect: 1, got: 2 });
+        assert_eq!(Failure::read("a", crc).kind, Kind::Crc);
+        let corrupt = io::Error::new(io::ErrorKind::InvalidData, "invalid block type");
+        assert_eq!(Failure::read("a", corrupt).kind, Kind::Decompress);
+    }
+
+    #[test]
+    fn tally_adds_up_archives() {
+        let tally = Tally::default();
+        tally.add(10, 2);
+        tally.add(5, 0);
+        assert_eq!(tally.processed.load(Ordering::Relaxed), 15);
+        assert_eq!(tally.failed.load(Ordering::Relaxed), 2);
+    }
+}
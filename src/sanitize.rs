@@ -0,0 +1,425 @@
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{ Arc, Mutex };
+use std::collections::HashMap;
+use camino::{ Utf8Path as Path, Utf8PathBuf as PathBuf };
+use crate::manifest;
+use crate::report::{ Status, Kind, Failure };
+
+
+/// What to do with entry names that aren't a plain relative path.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PathPolicy {
+    /// refuse to extract the entry.
+    Strict,
+    /// rewrite the name into a relative path and list the rewrites at the end.
+    Sanitize,
+    /// rewrite the name like `sanitize`, and warn about it right away.
+    Warn,
+}
+
+impl FromStr for PathPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<PathPolicy, String> {
+        match s {
+            "strict" => Ok(PathPolicy::Strict),
+            "sanitize" => Ok(PathPolicy::Sanitize),
+            "warn" => Ok(PathPolicy::Warn),
+            _ => Err(format!("unknown path policy `{}`, expected one of: strict, sanitize, warn", s))
+        }
+    }
+}
+
+/// Something unsafe about an entry name.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Issue {
+    Absolute,
+    ParentDir,
+    DriveLetter,
+    Backslash,
+    Nul,
+    Control,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Issue::Absolute => "absolute path",
+            Issue::ParentDir => "`..` component",
+            Issue::DriveLetter => "drive letter",
+            Issue::Backslash => "backslash",
+            Issue::Nul => "NUL character",
+            Issue::Control => "control character",
+        })
+    }
+}
+
+fn join_issues(issues: &[Issue]) -> String {
+    issues.iter()
+        .map(Issue::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// An entry name turned into a relative path.
+#[derive(Debug)]
+pub struct Sanitized {
+    pub path: PathBuf,
+    pub is_dir: bool,
+    pub issues: Vec<Issue>,
+}
+
+/// turn an entry name into a relative path, noting everything that had to change.
+///
+/// backslashes become separators, drive letters and leading separators are removed,
+/// `.` components are dropped, `..` components remove the previous component but never
+/// climb above the top and control characters are replaced by `_`.
+pub fn sanitize(name: &str) -> Sanitized {
+    let mut issues = Vec::new();
+    let mut note = |issue| if !issues.contains(&issue) {
+        issues.push(issue);
+    };
+
+    if name.contains('\\') {
+        note(Issue::Backslash);
+    }
+    let name = name.replace('\\', "/");
+    let is_dir = name.ends_with('/');
+
+    let mut rest = name.as_str();
+    let bytes = rest.as_bytes();
+    if bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' {
+        note(Issue::DriveLetter);
+        rest = &rest[2..];
+    }
+    if rest.starts_with('/') {
+        note(Issue::Absolute);
+    }
+
+    let mut path = PathBuf::new();
+    for component in rest.split('/') {
+        match component {
+            "" | "." => (),
+            ".." => {
+                note(Issue::ParentDir);
+                path.pop();
+            },
+            component => {
+                let component: String = component.chars()
+                    .map(|c| match c {
+                        '\0' => {
+                            note(Issue::Nul);
+                            '_'
+                        },
+                        c if c.is_control() => {
+                            note(Issue::Control);
+                            '_'
+                        },
+                        c => c
+                    })
+                    .collect();
+                path.push(component);
+            }
+        }
+    }
+
+    Sanitized { path, is_dir, issues }
+}
+
+/// Where an entry goes on disk.
+#[derive(Debug)]
+pub struct Destination {
+    pub path: PathBuf,
+    pub is_dir: bool,
+    /// what was rewritten to get there.
+    pub issues: Vec<Issue>,
+}
+
+#[derive(Debug)]
+pub struct Rewrite {
+    pub name: String,
+    pub path: PathBuf,
+    pub issues: Vec<Issue>,
+}
+
+/// Output paths taken by the entries of a run, across its archives.
+///
+/// the first entry to claim a path keeps it, entries are resolved in central
+/// directory order and archives one after the other, so that doesn't depend on the workers.
+#[derive(Default)]
+pub struct Claims {
+    taken: Mutex<Taken>,
+}
+
+#[derive(Default)]
+struct Taken {
+    files: HashMap<PathBuf, Owner>,
+    /// directories, of directory entries and the parents of every path.
+    dirs: HashMap<PathBuf, Owner>,
+}
+
+/// The entry that took a path.
+struct Owner {
+    archive: Arc<str>,
+    name: String,
+}
+
+impl Owner {
+    /// the entry, with its archive if that isn't `archive`.
+    fn describe(&self, archive: &str) -> String {
+        if *self.archive == *archive {
+            format!("`{}`", self.name)
+        } else {
+            format!("`{}` of {}", self.name, self.archive)
+        }
+    }
+}
+
+impl Taken {
+    /// take `path` for `owner`, unless it or one of its parents is taken in a way that conflicts.
+    fn claim(&mut self, path: &Path, is_dir: bool, owner: Owner) -> Result<(), String> {
+        if let Some(other) = self.files.get(path) {
+            return Err(format!("{} is also the output path of {}", path, other.describe(&owner.archive)))
+        }
+        if !is_dir {
+            if let Some(other) = self.dirs.get(path) {
+                return Err(format!("{} is a directory of {}", path, other.describe(&owner.archive)))
+            }
+        }
+        let parents = path.ancestors().skip(1).filter(|parent| !parent.as_str().is_empty());
+        for parent in parents.clone() {
+            if let Some(other) = self.files.get(parent) {
+                return Err(format!("{} is the output path of {}, not a directory", parent, other.describe(&owner.archive)))
+            }
+        }
+
+        for parent in parents {
+            if !self.dirs.contains_key(parent) {
+                let owner = Owner { archive: owner.archive.clone(), name: owner.name.clone() };
+                self.dirs.insert(parent.to_path_buf(), owner);
+            }
+        }
+        if is_dir {
+            self.dirs.entry(path.to_path_buf()).or_insert(owner);
+        } else {
+            self.files.insert(path.to_path_buf(), owner);
+        }
+        Ok(())
+    }
+}
+
+/// Resolves output paths for the entries of one archive under a policy,
+/// keeping track of rewrites and of entries whose paths conflict with others of the run.
+pub struct Resolver<'a> {
+    base: &'a Path,
+    policy: PathPolicy,
+    claims: &'a Claims,
+    archive: Arc<str>,
+    rewrites: Mutex<Vec<Rewrite>>,
+}
+
+impl<'a> Resolver<'a> {
+    pub fn new(base: &'a Path, policy: PathPolicy, claims: &'a Claims, archive: &Path) -> Resolver<'a> {
+        Resolver {
+            base,
+            policy,
+            claims,
+            archive: archive.as_str().into(),
+            rewrites: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn resolve(&self, name: &str) -> Result<Destination, Failure> {
+        let Sanitized { path, is_dir, issues } = sanitize(name);
+
+        if !issues.is_empty() {
+            match self.policy {
+                PathPolicy::Strict => return Err(Failure::new(name, Kind::UnsafePath, join_issues(&issues))),
+                PathPolicy::Warn => eprintln!("unzipx: warning: {:?}: {}, extracting to {}",
+                    name,
+                    join_issues(&issues),
+                    path
+                ),
+                PathPolicy::Sanitize => ()
+            }
+        }
+        if path.as_str().is_empty() {
+            return Err(Failure::new(name, Kind::UnsafePath, "nothing left of the name after sanitizing"))
+        }
+        // part files would race with the entry they belong to, a manifest with its records
+        if let Some(suffix) = manifest::RESERVED_SUFFIXES.iter().find(|suffix| path.as_str().ends_with(*suffix)) {
+            return Err(Failure::new(name, Kind::Collision, format_args!("{}: names ending in `{}` are reserved for unzipx", path, suffix)))
+        }
+
+        // several directory entries for the same directory are harmless
+        let owner = Owner { archive: self.archive.clone(), name: name.into() };
+        self.claims.taken.lock().unwrap()
+            .claim(&path, is_dir, owner)
+            .map_err(|message| Failure::new(name, Kind::Collision, message))?;
+
+        if !issues.is_empty() {
+            self.rewrites.lock().unwrap().push(Rewrite { name: name.into(), path: path.clone(), issues: issues.clone() });
+        }
+
+        Ok(Destination { path: self.base.join(&path), is_dir, issues })
+    }
+
+    /// sanitized names warn, unless they were rewritten quietly.
+    pub fn status(&self) -> Status {
+        match self.policy {
+            PathPolicy::Warn if !self.rewrites.lock().unwrap().is_empty() => Status::Warning,
+            _ => Status::Ok
+        }
+    }
+
+    /// print the rewrites to stderr, sorted by entry name.
+    pub fn eprint(&self) {
+        if self.policy != PathPolicy::Sanitize {
+            return
+        }
+
+        let mut rewrites = self.rewrites.lock().unwrap();
+        rewrites.sort_by(|a, b| a.name.cmp(&b.name));
+        for rewrite in rewrites.iter() {
+            eprintln!("unzipx: sanitized {:?} to {} ({})",
+                rewrite.name,
+                rewrite.path,
+                join_issues(&rewrite.issues)
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(name: &str, path: &str, issues: &[Issue]) {
+        let sanitized = sanitize(name);
+        assert_eq!(sanitized.path, path, "{:?}", name);
+        assert_eq!(sanitized.issues, issues, "{:?}", name);
+    }
+
+    #[test]
+    fn plain_names_are_kept() {
+        check("a/b.txt", "a/b.txt", &[]);
+        check("./a/./b.txt", "a/b.txt", &[]);
+        assert!(sanitize("a/b/").is_dir);
+    }
+
+    #[test]
+    fn parent_dirs_never_climb_out() {
+        check("../../etc/passwd", "etc/passwd", &[Issue::ParentDir]);
+        check("a/../b", "b", &[Issue::ParentDir]);
+        check("a/b/../../../c", "c", &[Issue::ParentDir]);
+    }
+
+    #[test]
+    fn absolute_paths_and_drive_letters() {
+        check("/etc/passwd", "etc/passwd", &[Issue::Absolute]);
+        check("C:foo.txt", "foo.txt", &[Issue::DriveLetter]);
+        check("c:\\windows\\win.ini", "windows/win.ini", &[Issue::Backslash, Issue::DriveLetter, Issue::Absolute]);
+    }
+
+    #[test]
+    fn backslashes_are_separators() {
+        check("a\\b\\c.txt", "a/b/c.txt", &[Issue::Backslash]);
+        check("..\\evil", "evil", &[Issue::Backslash, Issue::ParentDir]);
+    }
+
+    #[test]
+    fn control_characters_are_replaced() {
+        check("a\0b", "a_b", &[Issue::Nul]);
+        check("a\nb\x7f", "a_b_", &[Issue::Control]);
+    }
+
+    #[test]
+    fn empty_after_sanitizing_is_refused() {
+        check("../", "", &[Issue::ParentDir]);
+
+        let claims = Claims::default();
+        let resolver = Resolver::new(Path::new("out"), PathPolicy::Sanitize, &claims, Path::new("a.zip"));
+        for name in ["..", "/", "./.", "C:"] {
+            let failure = resolver.resolve(name).unwrap_err();
+            assert_eq!(failure.kind, Kind::UnsafePath, "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn strict_refuses_rewrites() {
+        let claims = Claims::default();
+        let resolver = Resolver::new(Path::new("out"), PathPolicy::Strict, &claims, Path::new("a.zip"));
+        assert_eq!(resolver.resolve("../x").unwrap_err().kind, Kind::UnsafePath);
+        assert_eq!(resolver.resolve("x").unwrap().path, "out/x");
+    }
+
+    #[test]
+    fn collisions_are_reported() {
+        let claims = Claims::default();
+        let resolver = Resolver::new(Path::new("out"), PathPolicy::Sanitize, &claims, Path::new("a.zip"));
+        assert_eq!(resolver.resolve("a/b").unwrap().path, "out/a/b");
+
+        for name in ["a\\b", "/a/b", "x/../a/b"] {
+            let failure = resolver.resolve(name).unwrap_err();
+            assert_eq!(failure.kind, Kind::Collision, "{:?}", name);
+        }
+
+        // directories may be listed more than once
+        resolver.resolve("d/").unwrap();
+        resolver.resolve("d/").unwrap();
+    }
+
+    #[test]
+    fn files_and_directories_collide() {
+        let claims = Claims::default();
+        let resolver = Resolver::new(Path::new("out"), PathPolicy::Sanitize, &claims, Path::new("a.zip"));
+        resolver.resolve("a").unwrap();
+        for (name, message) in [
+            ("a/b", "a is the output path of `a`, not a directory"),
+            ("a/c/d", "a is the output path of `a`, not a directory"),
+            ("a/", "a is also the output path of `a`"),
+        ] {
+            let failure = resolver.resolve(name).unwrap_err();
+            assert_eq!(failure.kind, Kind::Collision, "{:?}", name);
+            assert_eq!(failure.message, message);
+        }
+
+        resolver.resolve("x/y/z").unwrap();
+        for name in ["x", "x/y"] {
+            let failure = resolver.resolve(name).unwrap_err();
+            assert_eq!(failure.kind, Kind::Collision, "{:?}", name);
+            assert_eq!(failure.message, format!("{} is a directory of `x/y/z`", name));
+        }
+        // directory entries of the parents are fine
+        resolver.resolve("x/").unwrap();
+        resolver.resolve("x/y/").unwrap();
+        resolver.resolve("x/w").unwrap();
+    }
+
+    #[test]
+    fn collisions_across_archives() {
+        let claims = Claims::default();
+        let a = Resolver::new(Path::new("out"), PathPolicy::Sanitize, &claims, Path::new("a.zip"));
+        let b = Resolver::new(Path::new("out"), PathPolicy::Sanitize, &claims, Path::new("b.zip"));
+        a.resolve("x").unwrap();
+        a.resolve("d/").unwrap();
+
+        assert_eq!(b.resolve("x").unwrap_err().message, "x is also the output path of `x` of a.zip");
+        assert_eq!(b.resolve("x/y").unwrap_err().message, "x is the output path of `x` of a.zip, not a directory");
+        b.resolve("d/").unwrap();
+        b.resolve("d/y").unwrap();
+    }
+
+    #[test]
+    fn reserved_names() {
+        let claims = Claims::default();
+        let resolver = Resolver::new(Path::new("out"), PathPolicy::Sanitize, &claims, Path::new("a.zip"));
+        for name in ["a.unzipx-part", "d/.a.zip.unzipx-manifest"] {
+            let failure = resolver.resolve(name).unwrap_err();
+            assert_eq!(failure.kind, Kind::Collision, "{:?}", name);
+        }
+        resolver.resolve("a").unwrap();
+        resolver.resolve("a.unzipx-part.txt").unwrap();
+    }
+}
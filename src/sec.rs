@@ -0,0 +1,840 @@
+use std::{ fmt, iter };
+use std::cmp::Reverse;
+use std::str::FromStr;
+use std::borrow::Cow;
+use std::collections::HashSet;
+use serde::{ de, Deserialize, Deserializer, Serialize, Serializer };
+use time::{ Date, Month, OffsetDateTime, Time };
+use crate::forms::{ Form, ItemCode };
+
+
+/*
+   type SecData struct {
+   Cik string `json:"cik"`
+   EntityType string `json:"entityType"`
+   Sic string `json:"sic"`
+   SicDescription string `json:"sicDescription"`
+   InsiderTransactionForOwnerExists int `json:"insiderTransactionForOwnerExists"`
+   InsiderTransactionForIssuerExists int `json:"insiderTransactionForIssuerExists"`
+   Name string `json:"name"`
+   Tickers []string `json:"tickers"`
+   Exchanges []string `json:"exchanges"`
+   Ein string `json:"ein"`
+   Description string `json:"description"`
+   Website string `json:"website"`
+   InvestorWebsite string `json:"investorWebsite"`
+   Category string `json:"category"`
+   FiscalYearEnd string `json:"fiscalYearEnd"`
+   StateOfIncorporation string `json:"stateOfIncorporation"`
+   StateOfIncorporationDescription string `json:"stateOfIncorporationDescription"`
+   Addresses struct {
+   Mailing SecAddress `json:"mailing"`
+   Business SecAddress `json:"business"`
+   } `json:"addresses"`
+   Phone string `json:"phone"`
+   Flags string `json:"flags"`
+   FormerNames []SecFormerName `json:"formerNames"`
+   Filings struct {
+   Recent SecFilings `json:"recent"`
+   Files []SecFile `json:"files"`
+   } `json:"filings"`
+   }
+   */
+/// A company file of the SEC EDGAR `submissions.zip`, borrowing from the buffer simd-json unescaped in place.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all="camelCase")]
+#[allow(dead_code)]
+pub struct SecData<'a> {
+    pub cik: Cik,
+    pub entity_type: &'a str,
+    pub sic: &'a str,
+    #[serde(borrow)]
+    pub sic_description: Option<Cow<'a, str>>,
+    pub insider_transaction_for_owner_exists: i64,
+    pub insider_transaction_for_issuer_exists: i64,
+    #[serde(borrow)]
+    pub name: Cow<'a, str>,
+    #[serde(borrow)]
+    pub tickers: Vec<&'a str>,
+    /// `null` for tickers that aren't listed anywhere.
+    #[serde(borrow)]
+    pub exchanges: Vec<Option<&'a str>>,
+    pub ein: Option<&'a str>,
+    #[serde(borrow)]
+    pub description: Option<Cow<'a, str>>,
+    #[serde(borrow)]
+    pub website: Option<Cow<'a, str>>,
+    #[serde(borrow, default)]
+    pub investor_website: Option<Cow<'a, str>>,
+    /// filer status, several are separated by `<br>`.
+    #[serde(borrow)]
+    pub category: Option<Cow<'a, str>>,
+    /// `MMDD`.
+    pub fiscal_year_end: Option<&'a str>,
+    pub state_of_incorporation: Option<&'a str>,
+    pub state_of_incorporation_description: Option<&'a str>,
+    #[serde(borrow)]
+    pub addresses: SecAddresses<'a>,
+    pub phone: Option<&'a str>,
+    pub flags: Option<&'a str>,
+    #[serde(borrow)]
+    pub former_names: Vec<SecFormerName<'a>>,
+    #[serde(borrow)]
+    pub filings: SecRecentFilings<'a>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all="camelCase")]
+#[allow(dead_code)]
+pub struct SecAddresses<'a> {
+    #[serde(borrow)]
+    pub mailing: SecAddress<'a>,
+    #[serde(borrow)]
+    pub business: SecAddress<'a>,
+}
+
+/*
+   type SecAddress struct {
+   Street1 string `json:"street1"`
+   Street2 string `json:"street2"`
+   City string `json:"city"`
+   StateOrCountry string `json:"stateOrCountry"`
+   ZipCode string `json:"zipCode"`
+   StateOrCountryDescription string `json:"stateOrCountryDescription"`
+   }
+   */
+#[derive(Deserialize, Debug)]
+#[serde(rename_all="camelCase")]
+#[allow(dead_code)]
+pub struct SecAddress<'a> {
+    #[serde(borrow)]
+    pub street1: Option<Cow<'a, str>>,
+    #[serde(borrow)]
+    pub street2: Option<Cow<'a, str>>,
+    #[serde(borrow)]
+    pub city: Option<Cow<'a, str>>,
+    pub state_or_country: Option<&'a str>,
+    pub zip_code: Option<&'a str>,
+    pub state_or_country_description: Option<&'a str>,
+}
+
+/*
+   type SecFormerName struct {
+   Name string `json:"name"`
+   From string `json:"from"`
+   To string `json:"to"`
+   }
+   */
+#[derive(Deserialize, Debug)]
+#[serde(rename_all="camelCase")]
+#[allow(dead_code)]
+pub struct SecFormerName<'a> {
+    #[serde(borrow)]
+    pub name: Cow<'a, str>,
+    pub from: Option<&'a str>,
+    pub to: Option<&'a str>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all="camelCase")]
+#[allow(dead_code)]
+pub struct SecRecentFilings<'a> {
+    #[serde(borrow)]
+    pub recent: SecFilings<'a>,
+    #[serde(borrow)]
+    pub files: Vec<SecFile<'a>>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all="camelCase")]
+#[allow(dead_code)]
+pub struct SecFile<'a> {
+    pub name: &'a str,
+    pub filing_count: i64,
+    #[serde(deserialize_with = "cell")]
+    pub filing_from: Date,
+    #[serde(deserialize_with = "cell")]
+    pub filing_to: Date,
+}
+
+impl SecFile<'_> {
+    /// the company the file belongs to, from its name.
+    #[allow(dead_code)]
+    pub fn cik(&self) -> Result<Cik, InvalidValue> {
+        Cik::from_entry_name(self.name)
+    }
+
+    /// check a page read from the archive has as many filings as listed.
+    pub fn check(&self, page: &SecFilings) -> Result<(), PageMismatch> {
+        let len = page.len()?;
+        match usize::try_from(self.filing_count) {
+            Ok(count) if count == len => Ok(()),
+            _ => Err(PageMismatch::Count { len, expect: self.filing_count })
+        }
+    }
+}
+
+/// whether an entry is a `files` page, `CIK##########-submissions-###.json`, rather than a company file.
+pub fn is_page(name: &str) -> bool {
+    Cik::from_entry_name(name).is_ok() && name.contains("-submissions-")
+}
+
+/// A page that doesn't hold what `files` says it does.
+#[derive(Debug)]
+pub enum PageMismatch {
+    Columns(ColumnMismatch),
+    Count { len: usize, expect: i64 },
+}
+
+impl From<ColumnMismatch> for PageMismatch {
+    fn from(err: ColumnMismatch) -> PageMismatch {
+        PageMismatch::Columns(err)
+    }
+}
+
+impl fmt::Display for PageMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PageMismatch::Columns(err) => err.fmt(f),
+            PageMismatch::Count { len, expect } => write!(f, "{} filings, expected {}", len, expect),
+        }
+    }
+}
+
+impl std::error::Error for PageMismatch {}
+
+/// the complete filing history of a company, its recent filings and those of its `files` pages.
+///
+/// each accession number is kept once, the first seen, and filings are newest first like in `recent`.
+pub fn history<'a, 'b>(recent: &'a SecFilings<'b>, pages: &'a [SecFilings<'b>])
+    -> Result<History<'a, 'b>, ColumnMismatch>
+{
+    let parts: Vec<_> = iter::once(recent).chain(pages).collect();
+    let mut seen = HashSet::new();
+    let mut rows = Vec::new();
+    for (part, filings) in parts.iter().enumerate() {
+        rows.extend(filings.filings()?
+            .enumerate()
+            .filter(|(_, filing)| seen.insert(filing.accession_number))
+            .map(|(index, _)| Row { part, index }));
+    }
+
+    rows.sort_by_key(|row| {
+        let part = parts[row.part];
+        Reverse((part.filing_date[row.index], part.acceptance_date_time[row.index]))
+    });
+    Ok(History { parts, rows })
+}
+
+/// The filings of a company, rows into the columns of its recent filings and pages.
+pub struct History<'a, 'b> {
+    parts: Vec<&'a SecFilings<'b>>,
+    /// newest first.
+    pub rows: Vec<Row>,
+}
+
+/// A filing of a `History`, the row `index` of the columns of part `part`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Row {
+    part: usize,
+    index: usize,
+}
+
+impl<'a, 'b> History<'a, 'b> {
+    pub fn filing(&self, row: Row) -> Filing<'a> {
+        self.parts[row.part].filing(row.index)
+    }
+
+    /// the values of a column in `rows`, taken from the column vectors of each part.
+    pub fn column<'c, T: 'a, F>(&'c self, rows: &'c [Row], column: F)
+        -> impl ExactSizeIterator<Item = &'a T> + use<'a, 'b, 'c, T, F>
+    where
+        F: Fn(&'a SecFilings<'b>) -> &'a [T]
+    {
+        rows.iter().map(move |row| &column(self.parts[row.part])[row.index])
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all="camelCase")]
+#[allow(dead_code)]
+pub struct SecFilings<'a> {
+    pub accession_number: Vec<AccessionNumber>,
+    #[serde(deserialize_with = "column")]
+    pub filing_date: Vec<Date>,
+    /// blank for forms that aren't about a period.
+    #[serde(deserialize_with = "column")]
+    pub report_date: Vec<Option<Date>>,
+    #[serde(deserialize_with = "column")]
+    pub acceptance_date_time: Vec<OffsetDateTime>,
+    #[serde(borrow)]
+    pub act: Vec<&'a str>,
+    pub form: Vec<Form>,
+    #[serde(borrow)]
+    pub file_number: Vec<&'a str>,
+    #[serde(borrow)]
+    pub items: Vec<&'a str>,
+    pub size: Vec<Option<i64>>,
+    #[serde(rename="isXBRL")]
+    pub is_xbrl: Vec<i64>,
+    #[serde(rename="isInlineXBRL")]
+    pub is_inline_xbrl: Vec<i64>,
+    #[serde(borrow)]
+    pub primary_document: Vec<&'a str>,
+    #[serde(borrow)]
+    pub primary_doc_description: Vec<Cow<'a, str>>,
+}
+
+/*
+   type SecFiling struct {
+   AccessionNumber string `json:"accessionNumber"`
+   FilingDate string `json:"filingDate"`
+   ReportDate string `json:"reportDate"`
+   AcceptanceDateTime string `json:"acceptanceDateTime"`
+   Act string `json:"act"`
+   Form string `json:"form"`
+   FileNumber string `json:"fileNumber"`
+   Items string `json:"items"`
+   Size int `json:"size"`
+   IsXBRL int `json:"isXBRL"`
+   IsInlineXBRL int `json:"isInlineXBRL"`
+   PrimaryDocument string `json:"primaryDocument"`
+   PrimaryDocDescription string `json:"primaryDocDescription"`
+   }
+   */
+#[derive(Debug)]
+#[allow(dead_code)]
+pub struct Filing<'a> {
+    pub accession_number: AccessionNumber,
+    pub filing_date: Date,
+    pub report_date: Option<Date>,
+    pub acceptance_date_time: OffsetDateTime,
+    pub act: &'a str,
+    pub form: &'a Form,
+    pub file_number: &'a str,
+    pub items: &'a str,
+    pub size: Option<i64>,
+    pub is_xbrl: i64,
+    pub is_inline_xbrl: i64,
+    pub primary_document: &'a str,
+    pub primary_doc_description: &'a str,
+}
+
+impl Filing<'_> {
+    /// the 8-K items of the `items` column.
+    pub fn items(&self) -> Result<Vec<ItemCode>, InvalidValue> {
+        ItemCode::parse_list(self.items)
+    }
+}
+
+/// A column of `SecFilings` with a different number of rows than `accessionNumber`.
+#[derive(Debug)]
+pub struct ColumnMismatch {
+    pub column: &'static str,
+    pub len: usize,
+    pub expect: usize,
+}
+
+impl fmt::Display for ColumnMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "column `{}` has {} rows, expected {}", self.column, self.len, self.expect)
+    }
+}
+
+impl std::error::Error for ColumnMismatch {}
+
+impl<'a> SecFilings<'a> {
+    /// the number of rows, after checking that every column has it.
+    pub fn len(&self) -> Result<usize, ColumnMismatch> {
+        let expect = self.accession_number.len();
+        let columns = [
+            ("filingDate", self.filing_date.len()),
+            ("reportDate", self.report_date.len()),
+            ("acceptanceDateTime", self.acceptance_date_time.len()),
+            ("act", self.act.len()),
+            ("form", self.form.len()),
+            ("fileNumber", self.file_number.len()),
+            ("items", self.items.len()),
+            ("size", self.size.len()),
+            ("isXBRL", self.is_xbrl.len()),
+            ("isInlineXBRL", self.is_inline_xbrl.len()),
+            ("primaryDocument", self.primary_document.len()),
+            ("primaryDocDescription", self.primary_doc_description.len()),
+        ];
+
+        match columns.into_iter().find(|&(_, len)| len != expect) {
+            Some((column, len)) => Err(ColumnMismatch { column, len, expect }),
+            None => Ok(expect)
+        }
+    }
+
+    /// the filings row by row, or an error if the columns have different lengths.
+    pub fn filings(&self) -> Result<impl ExactSizeIterator<Item = Filing<'_>>, ColumnMismatch> {
+        let len = self.len()?;
+        Ok((0..len).map(|i| self.filing(i)))
+    }
+
+    /// the filing of row `i`, the columns must have been checked by `len`.
+    fn filing(&self, i: usize) -> Filing<'_> {
+        Filing {
+            accession_number: self.accession_number[i],
+            filing_date: self.filing_date[i],
+            report_date: self.report_date[i],
+            acceptance_date_time: self.acceptance_date_time[i],
+            act: self.act[i],
+            form: &self.form[i],
+            file_number: self.file_number[i],
+            items: self.items[i],
+            size: self.size[i],
+            is_xbrl: self.is_xbrl[i],
+            is_inline_xbrl: self.is_inline_xbrl[i],
+            primary_document: self.primary_document[i],
+            primary_doc_description: &self.primary_doc_description[i],
+        }
+    }
+}
+
+/// A value of the SEC data that couldn't be parsed.
+#[derive(PartialEq, Eq, Debug)]
+pub struct InvalidValue {
+    pub kind: &'static str,
+    pub input: String,
+}
+
+impl InvalidValue {
+    pub fn new(kind: &'static str, input: &str) -> InvalidValue {
+        InvalidValue { kind, input: input.into() }
+    }
+}
+
+impl fmt::Display for InvalidValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid {} `{}`", self.kind, self.input)
+    }
+}
+
+impl std::error::Error for InvalidValue {}
+
+fn parse_digits<T: FromStr>(s: &str, len: usize) -> Option<T> {
+    if s.len() == len && s.bytes().all(|b| b.is_ascii_digit()) {
+        s.parse().ok()
+    } else {
+        None
+    }
+}
+
+/// Central Index Key, the SEC's identifier of a filer, up to 10 digits.
+///
+/// parses from plain or zero-padded digits, `CIK0000320193` and entry names like `CIK0000320193.json`.
+/// displays and serializes zero-padded.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Cik(pub u64);
+
+impl Cik {
+    const MAX: u64 = 9_999_999_999;
+
+    /// the CIK of `CIK##########.json` and `CIK##########-submissions-###.json`.
+    pub fn from_entry_name(name: &str) -> Result<Cik, InvalidValue> {
+        let is_page = |rest: &str| rest.strip_prefix("-submissions-")
+            .and_then(|rest| rest.strip_suffix(".json"))
+            .and_then(|number| parse_digits::<u16>(number, 3))
+            .is_some();
+
+        name.strip_prefix("CIK")
+            .and_then(|rest| rest.split_at_checked(10))
+            .filter(|(_, rest)| *rest == ".json" || is_page(rest))
+            .and_then(|(digits, _)| parse_digits(digits, 10))
+            .map(Cik)
+            .ok_or_else(|| InvalidValue::new("CIK entry name", name))
+    }
+
+    /// the name of the company file in `submissions.zip`.
+    #[allow(dead_code)]
+    pub fn entry_name(self) -> String {
+        format!("CIK{}.json", self)
+    }
+}
+
+impl FromStr for Cik {
+    type Err = InvalidValue;
+
+    fn from_str(s: &str) -> Result<Cik, InvalidValue> {
+        if let Some(digits) = s.strip_prefix("CIK") {
+            return match parse_digits(digits, 10) {
+                Some(cik) => Ok(Cik(cik)),
+                None => Cik::from_entry_name(s)
+            }
+        }
+        (1..=10).contains(&s.len())
+            .then(|| parse_digits(s, s.len()))
+            .flatten()
+            .map(Cik)
+            .ok_or_else(|| InvalidValue::new("CIK", s))
+    }
+}
+
+impl fmt::Display for Cik {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:010}", self.0)
+    }
+}
+
+impl Serialize for Cik {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Cik {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Cik, D::Error> {
+        struct CikVisitor;
+
+        impl de::Visitor<'_> for CikVisitor {
+            type Value = Cik;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a CIK as an integer or a string of digits")
+            }
+
+            fn visit_u64<E: de::Error>(self, n: u64) -> Result<Cik, E> {
+                if n <= Cik::MAX {
+                    Ok(Cik(n))
+                } else {
+                    Err(E::custom(InvalidValue::new("CIK", &n.to_string())))
+                }
+            }
+
+            fn visit_i64<E: de::Error>(self, n: i64) -> Result<Cik, E> {
+                match u64::try_from(n) {
+                    Ok(n) => self.visit_u64(n),
+                    Err(_) => Err(E::custom(InvalidValue::new("CIK", &n.to_string())))
+                }
+            }
+
+            fn visit_str<E: de::Error>(self, s: &str) -> Result<Cik, E> {
+                s.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(CikVisitor)
+    }
+}
+
+/// Identifier of a filing, `0000320193-23-000106`: the CIK of the filer agent,
+/// the two-digit year and a sequence number of the agent within that year.
+///
+/// parses with or without dashes, displays and serializes with them.
+/// orders like its dashed form.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct AccessionNumber {
+    pub filer: Cik,
+    pub year: u8,
+    pub sequence: u32,
+}
+
+impl FromStr for AccessionNumber {
+    type Err = InvalidValue;
+
+    fn from_str(s: &str) -> Result<AccessionNumber, InvalidValue> {
+        let parts = match s.len() {
+            20 if s.as_bytes()[10] == b'-' && s.as_bytes()[13] == b'-' => Some((&s[..10], &s[11..13], &s[14..])),
+            18 => s.get(..10).zip(s.get(10..12)).zip(s.get(12..)).map(|((a, b), c)| (a, b, c)),
+            _ => None
+        };
+
+        parts
+            .and_then(|(filer, year, sequence)| Some(AccessionNumber {
+                filer: Cik(parse_digits(filer, 10)?),
+                year: parse_digits(year, 2)?,
+                sequence: parse_digits(sequence, 6)?,
+            }))
+            .ok_or_else(|| InvalidValue::new("accession number", s))
+    }
+}
+
+impl fmt::Display for AccessionNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{:02}-{:06}", self.filer, self.year, self.sequence)
+    }
+}
+
+impl Serialize for AccessionNumber {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for AccessionNumber {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<AccessionNumber, D::Error> {
+        struct AccessionNumberVisitor;
+
+        impl de::Visitor<'_> for AccessionNumberVisitor {
+            type Value = AccessionNumber;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("an accession number like 0000320193-23-000106")
+            }
+
+            fn visit_str<E: de::Error>(self, s: &str) -> Result<AccessionNumber, E> {
+                s.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_str(AccessionNumberVisitor)
+    }
+}
+
+/// a date like `2023-11-03`.
+pub fn parse_date(s: &str) -> Result<Date, InvalidValue> {
+    let b = s.as_bytes();
+    let date = (b.len() == 10 && b[4] == b'-' && b[7] == b'-')
+        .then(|| Some((parse_digits(&s[..4], 4)?, parse_digits::<u8>(&s[5..7], 2)?, parse_digits(&s[8..], 2)?)))
+        .flatten()
+        .and_then(|(year, month, day)| Date::from_calendar_date(year, Month::try_from(month).ok()?, day).ok());
+    date.ok_or_else(|| InvalidValue::new("date", s))
+}
+
+/// a UTC timestamp like `2023-11-02T18:08:27.000Z`, the fraction is optional.
+pub fn parse_timestamp(s: &str) -> Result<OffsetDateTime, InvalidValue> {
+    let invalid = || InvalidValue::new("timestamp", s);
+
+    let (date, rest) = s.split_once('T').ok_or_else(invalid)?;
+    let date = parse_date(date).map_err(|_| invalid())?;
+    let rest = rest.strip_suffix('Z').ok_or_else(invalid)?;
+    let (hms, nanos) = match rest.split_once('.') {
+        Some((hms, fraction)) if (1..=9).contains(&fraction.len()) => {
+            let digits = parse_digits::<u32>(fraction, fraction.len()).ok_or_else(invalid)?;
+            (hms, digits * 10u32.pow(9 - fraction.len() as u32))
+        },
+        Some(_) => return Err(invalid()),
+        None => (rest, 0)
+    };
+
+    let b = hms.as_bytes();
+    let time = (b.len() == 8 && b[2] == b':' && b[5] == b':')
+        .then(|| Some((parse_digits(&hms[..2], 2)?, parse_digits(&hms[3..5], 2)?, parse_digits(&hms[6..], 2)?)))
+        .flatten()
+        .and_then(|(hour, minute, second)| Time::from_hms_nano(hour, minute, second, nanos).ok())
+        .ok_or_else(invalid)?;
+
+    Ok(date.with_time(time).assume_utc())
+}
+
+/// Displays a timestamp the way the SEC data writes it, `2023-11-02T18:08:27.000Z`.
+pub struct Timestamp(pub OffsetDateTime);
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let utc = self.0.to_offset(time::UtcOffset::UTC);
+        write!(f, "{}T{:02}:{:02}:{:02}.{:03}Z",
+            utc.date(), utc.hour(), utc.minute(), utc.second(), utc.millisecond())
+    }
+}
+
+/// A value of a date or timestamp column, to deserialize into `time` types without an intermediate string.
+struct Cell<T>(T);
+
+struct DateVisitor;
+
+impl de::Visitor<'_> for DateVisitor {
+    type Value = Option<Date>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a date like 2023-11-03")
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<Option<Date>, E> {
+        if s.is_empty() {
+            Ok(None)
+        } else {
+            parse_date(s).map(Some).map_err(E::custom)
+        }
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Option<Date>, E> {
+        Ok(None)
+    }
+}
+
+impl<'de> Deserialize<'de> for Cell<Option<Date>> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(DateVisitor).map(Cell)
+    }
+}
+
+impl<'de> Deserialize<'de> for Cell<Date> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match deserializer.deserialize_any(DateVisitor)? {
+            Some(date) => Ok(Cell(date)),
+            None => Err(de::Error::custom(InvalidValue::new("date", "")))
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Cell<OffsetDateTime> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct TimestampVisitor;
+
+        impl de::Visitor<'_> for TimestampVisitor {
+            type Value = OffsetDateTime;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a timestamp like 2023-11-02T18:08:27.000Z")
+            }
+
+            fn visit_str<E: de::Error>(self, s: &str) -> Result<OffsetDateTime, E> {
+                parse_timestamp(s).map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_str(TimestampVisitor).map(Cell)
+    }
+}
+
+fn cell<'de, D: Deserializer<'de>, T>(deserializer: D) -> Result<T, D::Error>
+where
+    Cell<T>: Deserialize<'de>
+{
+    Cell::<T>::deserialize(deserializer).map(|cell| cell.0)
+}
+
+fn column<'de, D: Deserializer<'de>, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    Cell<T>: Deserialize<'de>
+{
+    let column = Vec::<Cell<T>>::deserialize(deserializer)?;
+    Ok(column.into_iter().map(|cell| cell.0).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cik_from_digits() {
+        assert_eq!("320193".parse(), Ok(Cik(320193)));
+        assert_eq!("0000320193".parse(), Ok(Cik(320193)));
+        assert_eq!("9999999999".parse(), Ok(Cik(Cik::MAX)));
+        for s in ["", "12345678901", "+320193", "32019x", " 320193"] {
+            assert!(s.parse::<Cik>().is_err(), "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn cik_from_names() {
+        assert_eq!("CIK0000320193".parse(), Ok(Cik(320193)));
+        assert_eq!("CIK0000320193.json".parse(), Ok(Cik(320193)));
+        assert_eq!(Cik::from_entry_name("CIK0000320193-submissions-001.json"), Ok(Cik(320193)));
+        for name in [
+            "CIK320193.json",
+            "CIK0000320193",
+            "CIK0000320193.json.bak",
+            "CIK0000320193.jsonx",
+            "CIK0000320193-submissions-001.json.tmp",
+            "CIK0000320193-submissions-1.json",
+            "CIK0000320193-submissions-0001.json",
+            "CIK000032019x.json",
+            "cik0000320193.json",
+        ] {
+            assert!(Cik::from_entry_name(name).is_err(), "{:?}", name);
+        }
+        assert!("CIK0000320193.json.bak".parse::<Cik>().is_err());
+    }
+
+    #[test]
+    fn cik_round_trip() {
+        let cik = Cik(320193);
+        assert_eq!(cik.to_string(), "0000320193");
+        assert_eq!(cik.to_string().parse(), Ok(cik));
+        assert_eq!(cik.entry_name(), "CIK0000320193.json");
+        assert_eq!(Cik::from_entry_name(&cik.entry_name()), Ok(cik));
+
+        assert_eq!(serde_json::to_string(&cik).unwrap(), r#""0000320193""#);
+        assert_eq!(serde_json::from_str::<Cik>("320193").unwrap(), cik);
+        assert_eq!(serde_json::from_str::<Cik>(r#""0000320193""#).unwrap(), cik);
+        assert!(serde_json::from_str::<Cik>("10000000000").is_err());
+        assert!(serde_json::from_str::<Cik>("-1").is_err());
+    }
+
+    #[test]
+    fn pages() {
+        assert!(is_page("CIK0000320193-submissions-001.json"));
+        assert!(!is_page("CIK0000320193.json"));
+        assert!(!is_page("CIK0000320193-submissions-001.json.tmp"));
+        assert!(!is_page("x/CIK0000320193-submissions-001.json"));
+    }
+
+    #[test]
+    fn accession_numbers() {
+        let expect = AccessionNumber { filer: Cik(320193), year: 23, sequence: 106 };
+        assert_eq!("0000320193-23-000106".parse(), Ok(expect));
+        assert_eq!("000032019323000106".parse(), Ok(expect));
+        for s in [
+            "",
+            "0000320193-23-00010",
+            "000032019323-000106",
+            "0000320193--23000106",
+            "0000320193-2x-000106",
+            "00003201932300010x",
+            "0000320193-23-0001066",
+        ] {
+            assert!(s.parse::<AccessionNumber>().is_err(), "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn accession_number_round_trip() {
+        for s in ["0000320193-23-000106", "0001193125-99-000001"] {
+            let accession_number: AccessionNumber = s.parse().unwrap();
+            assert_eq!(accession_number.to_string(), s);
+            let json = serde_json::to_string(&accession_number).unwrap();
+            assert_eq!(serde_json::from_str::<AccessionNumber>(&json).unwrap(), accession_number);
+        }
+        // dashed order is numeric order
+        assert!("0000320193-23-000106".parse::<AccessionNumber>().unwrap()
+            < "0000320193-24-000001".parse().unwrap());
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(parse_date("2023-11-03"), Ok(Date::from_calendar_date(2023, Month::November, 3).unwrap()));
+        assert_eq!(parse_date("2024-02-29").unwrap().to_string(), "2024-02-29");
+        for s in ["", "2023-11-3", "2023/11/03", "2023-02-29", "2023-13-01", "20231103", "2023-11-03T00:00:00Z"] {
+            assert!(parse_date(s).is_err(), "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn timestamps() {
+        let expect = Date::from_calendar_date(2023, Month::November, 2).unwrap()
+            .with_hms(18, 8, 27).unwrap()
+            .assume_utc();
+        assert_eq!(parse_timestamp("2023-11-02T18:08:27Z"), Ok(expect));
+        assert_eq!(parse_timestamp("2023-11-02T18:08:27.000Z"), Ok(expect));
+
+        let millis = parse_timestamp("2023-11-02T18:08:27.123Z").unwrap();
+        assert_eq!(millis.millisecond(), 123);
+        assert_eq!(parse_timestamp("2023-11-02T18:08:27.5Z").unwrap().millisecond(), 500);
+        assert_eq!(parse_timestamp("2023-11-02T18:08:27.123456789Z").unwrap().nanosecond(), 123_456_789);
+        assert_eq!(Timestamp(millis).to_string(), "2023-11-02T18:08:27.123Z");
+        assert_eq!(Timestamp(expect).to_string(), "2023-11-02T18:08:27.000Z");
+    }
+
+    #[test]
+    fn invalid_timestamps() {
+        for s in [
+            "",
+            "2023-11-02",
+            "2023-11-02T18:08:27",
+            "2023-11-02 18:08:27Z",
+            "2023-11-02T18:08Z",
+            "2023-11-02T24:00:00Z",
+            "2023-11-02T18:08:27.Z",
+            "2023-11-02T18:08:27.1234567890Z",
+            "2023-11-02T18:08:27.12aZ",
+            "2023-11-02T18:08:27+01:00",
+        ] {
+            assert!(parse_timestamp(s).is_err(), "{:?}", s);
+        }
+    }
+}
@@ -0,0 +1,225 @@
+//! archives built in memory for the tests, in the zip64 layout `Zip64Archive` reads.
+
+use std::fs;
+use std::io::Write;
+use camino::{ Utf8Path as Path, Utf8PathBuf as PathBuf };
+use flate2::write::DeflateEncoder;
+use zip_parser::{ compress, system };
+use crate::sec::SecData;
+
+
+/// 2023-11-03 12:00:00 in MS-DOS format.
+pub const MOD_DATE: u16 = ((2023 - 1980) << 9) | (11 << 5) | 3;
+pub const MOD_TIME: u16 = 12 << 11;
+
+/// An entry of an archive to build, deflated unless told otherwise.
+pub struct Entry {
+    pub name: Vec<u8>,
+    pub data: Vec<u8>,
+    pub method: u16,
+    /// the crc32 of `data` unless set.
+    pub crc32: Option<u32>,
+    pub gp_flag: u16,
+    pub extra: Vec<u8>,
+    /// unix permissions, the entry is made by a DOS system without.
+    pub mode: Option<u32>,
+}
+
+impl Entry {
+    pub fn new(name: impl Into<Vec<u8>>, data: impl Into<Vec<u8>>) -> Entry {
+        Entry {
+            name: name.into(),
+            data: data.into(),
+            method: compress::DEFLATE,
+            crc32: None,
+            gp_flag: 0,
+            extra: Vec::new(),
+            mode: None,
+        }
+    }
+
+    pub fn dir(name: &str) -> Entry {
+        Entry::new(name, "").method(compress::STORE)
+    }
+
+    /// any method but deflate keeps `data` as it is.
+    pub fn method(mut self, method: u16) -> Entry {
+        self.method = method;
+        self
+    }
+
+    pub fn crc32(mut self, crc32: u32) -> Entry {
+        self.crc32 = Some(crc32);
+        self
+    }
+
+    pub fn flag(mut self, flag: u16) -> Entry {
+        self.gp_flag |= flag;
+        self
+    }
+
+    /// append an extra field to the central directory header.
+    pub fn extra(mut self, id: u16, data: &[u8]) -> Entry {
+        self.extra.extend_from_slice(&id.to_le_bytes());
+        self.extra.extend_from_slice(&(data.len() as u16).to_le_bytes());
+        self.extra.extend_from_slice(data);
+        self
+    }
+}
+
+/// the data of a Unicode Path or Comment extra field for the raw field `raw`.
+pub fn unicode_field(raw: &[u8], text: &str) -> Vec<u8> {
+    let mut data = vec![1];
+    data.extend_from_slice(&crc32fast::hash(raw).to_le_bytes());
+    data.extend_from_slice(text.as_bytes());
+    data
+}
+
+pub fn zip(entries: &[Entry]) -> Vec<u8> {
+    zip_with_comment(entries, b"")
+}
+
+pub fn zip_with_comment(entries: &[Entry], comment: &[u8]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    let mut cd = Vec::new();
+
+    for entry in entries {
+        let data = match entry.method {
+            compress::DEFLATE => {
+                let mut encoder = DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(&entry.data).unwrap();
+                encoder.finish().unwrap()
+            },
+            _ => entry.data.clone()
+        };
+        let crc32 = entry.crc32.unwrap_or_else(|| crc32fast::hash(&entry.data));
+        let lfh_offset = buf.len() as u32;
+
+        let mut common = Vec::new();
+        common.extend_from_slice(&20u16.to_le_bytes());
+        common.extend_from_slice(&entry.gp_flag.to_le_bytes());
+        common.extend_from_slice(&entry.method.to_le_bytes());
+        common.extend_from_slice(&MOD_TIME.to_le_bytes());
+        common.extend_from_slice(&MOD_DATE.to_le_bytes());
+        common.extend_from_slice(&crc32.to_le_bytes());
+        common.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        common.extend_from_slice(&(entry.data.len() as u32).to_le_bytes());
+        common.extend_from_slice(&(entry.name.len() as u16).to_le_bytes());
+
+        buf.extend_from_slice(b"PK\x03\x04");
+        buf.extend_from_slice(&common);
+        buf.extend_from_slice(&0u16.to_le_bytes());
+        buf.extend_from_slice(&entry.name);
+        buf.extend_from_slice(&data);
+
+        let made_by = if entry.mode.is_some() { system::UNIX << 8 | 20 } else { 20 };
+        cd.extend_from_slice(b"PK\x01\x02");
+        cd.extend_from_slice(&made_by.to_le_bytes());
+        cd.extend_from_slice(&common);
+        cd.extend_from_slice(&(entry.extra.len() as u16).to_le_bytes());
+        // comment length, disk number, internal attributes
+        cd.extend_from_slice(&[0; 6]);
+        cd.extend_from_slice(&(entry.mode.unwrap_or(0) << 16).to_le_bytes());
+        cd.extend_from_slice(&lfh_offset.to_le_bytes());
+        cd.extend_from_slice(&entry.name);
+        cd.extend_from_slice(&entry.extra);
+    }
+
+    let cd_offset = buf.len() as u64;
+    let cd_size = cd.len() as u64;
+    let count = entries.len() as u64;
+    buf.extend_from_slice(&cd);
+
+    let zip64_eocdr_offset = buf.len() as u64;
+    buf.extend_from_slice(b"PK\x06\x06");
+    buf.extend_from_slice(&44u64.to_le_bytes());
+    buf.extend_from_slice(&45u16.to_le_bytes());
+    buf.extend_from_slice(&45u16.to_le_bytes());
+    buf.extend_from_slice(&[0; 8]);
+    buf.extend_from_slice(&count.to_le_bytes());
+    buf.extend_from_slice(&count.to_le_bytes());
+    buf.extend_from_slice(&cd_size.to_le_bytes());
+    buf.extend_from_slice(&cd_offset.to_le_bytes());
+
+    buf.extend_from_slice(b"PK\x06\x07");
+    buf.extend_from_slice(&0u32.to_le_bytes());
+    buf.extend_from_slice(&zip64_eocdr_offset.to_le_bytes());
+    buf.extend_from_slice(&1u32.to_le_bytes());
+
+    buf.extend_from_slice(b"PK\x05\x06");
+    buf.extend_from_slice(&[0; 4]);
+    buf.extend_from_slice(&(count as u16).to_le_bytes());
+    buf.extend_from_slice(&(count as u16).to_le_bytes());
+    buf.extend_from_slice(&(cd_size as u32).to_le_bytes());
+    buf.extend_from_slice(&(cd_offset as u32).to_le_bytes());
+    buf.extend_from_slice(&(comment.len() as u16).to_le_bytes());
+    buf.extend_from_slice(comment);
+    buf
+}
+
+/// write an archive of `entries` to `dir/name`.
+pub fn write_zip(dir: &Path, name: &str, entries: &[Entry]) -> PathBuf {
+    let path = dir.join(name);
+    fs::write(&path, zip(entries)).unwrap();
+    path
+}
+
+/// a company of the SEC EDGAR `submissions.zip`, with two recent filings.
+pub const COMPANY: &str = r#"{
+    "cik": "320193",
+    "entityType": "operating",
+    "sic": "3571",
+    "sicDescription": "Electronic Computers",
+    "insiderTransactionForOwnerExists": 0,
+    "insiderTransactionForIssuerExists": 1,
+    "name": "Apple Inc.",
+    "tickers": ["AAPL"],
+    "exchanges": ["Nasdaq"],
+    "ein": "942404110",
+    "description": "",
+    "website": "",
+    "investorWebsite": "",
+    "category": "Large accelerated filer",
+    "fiscalYearEnd": "0930",
+    "stateOfIncorporation": "CA",
+    "stateOfIncorporationDescription": "CA",
+    "addresses": {
+        "mailing": {"street1": "ONE APPLE PARK WAY", "street2": null, "city": "CUPERTINO", "stateOrCountry": "CA", "zipCode": "95014", "stateOrCountryDescription": "CA"},
+        "business": {"street1": "ONE APPLE PARK WAY", "street2": null, "city": "CUPERTINO", "stateOrCountry": "CA", "zipCode": "95014", "stateOrCountryDescription": "CA"}
+    },
+    "phone": "(408) 996-1010",
+    "flags": "",
+    "formerNames": [{"name": "APPLE COMPUTER INC", "from": "1994-01-26T00:00:00.000Z", "to": "2019-08-05T00:00:00.000Z"}],
+    "filings": {
+        "recent": {
+            "accessionNumber": ["0000320193-23-000106", "0001140361-23-039575"],
+            "filingDate": ["2023-11-03", "2023-08-10"],
+            "reportDate": ["2023-09-30", ""],
+            "acceptanceDateTime": ["2023-11-02T18:08:27.000Z", "2023-08-10T18:31:33.000Z"],
+            "act": ["34", ""],
+            "form": ["10-K", "8-K/A"],
+            "fileNumber": ["001-36743", ""],
+            "filmNumber": ["231373899", ""],
+            "items": ["", "5.02,9.01"],
+            "core_type": ["10-K", "8-K"],
+            "size": [9773415, 4712],
+            "isXBRL": [1, 0],
+            "isInlineXBRL": [1, 0],
+            "primaryDocument": ["aapl-20230930.htm", "d519112d8ka.htm"],
+            "primaryDocDescription": ["10-K", "FORM 8-K, \"amended\""]
+        },
+        "files": []
+    }
+}"#;
+
+/// a company parsed from the JSON in `data`, like `--parse` does.
+pub fn company(data: &mut [u8]) -> SecData<'_> {
+    simd_json::serde::from_slice(data).unwrap()
+}
+
+/// a temporary directory, removed on drop.
+pub fn temp_dir() -> (tempfile::TempDir, PathBuf) {
+    let dir = tempfile::tempdir().unwrap();
+    let path = PathBuf::from_path_buf(dir.path().to_path_buf()).unwrap();
+    (dir, path)
+}
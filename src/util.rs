@@ -1,8 +1,8 @@
-use std::{ io, fs };
+use std::{ io, fs, fmt };
 use anyhow::Context;
 use flate2::bufread::DeflateDecoder;
 use zstd::stream::read::Decoder as ZstdDecoder;
-use camino::{ Utf8Path as Path, Utf8PathBuf as PathBuf, Utf8Component as Component };
+use camino::Utf8Path as Path;
 
 
 pub enum Decoder<R: io::BufRead> {
@@ -21,6 +21,20 @@ impl<R: io::BufRead> io::Read for Decoder<R> {
     }
 }
 
+#[derive(Debug)]
+pub struct Crc32Mismatch {
+    pub expect: u32,
+    pub got: u32,
+}
+
+impl fmt::Display for Crc32Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "crc32 check failed. expect: {}, got: {}", self.expect, self.got)
+    }
+}
+
+impl std::error::Error for Crc32Mismatch {}
+
 pub struct Crc32Checker<R> {
     reader: R,
     expect: u32,
@@ -43,11 +57,8 @@ impl<R: io::Read> io::Read for Crc32Checker<R> {
         if n == 0 {
             let crc = self.hasher.clone().finalize();
             if crc != self.expect {
-                let msg = format!("crc32 check failed. expect: {}, got: {}",
-                    self.expect,
-                    crc
-                );
-                return Err(io::Error::new(io::ErrorKind::InvalidData, msg))
+                let err = Crc32Mismatch { expect: self.expect, got: crc };
+                return Err(io::Error::new(io::ErrorKind::InvalidData, err))
             }
         } else {
             self.hasher.update(&buf[..n]);
@@ -85,27 +96,9 @@ pub fn dos2time(dos_date: u16, dos_time: u16)
     Ok(date.with_time(time))
 }
 
-pub fn path_join(base: &Path, path: &Path) -> PathBuf {
-    let (_, path) = path.components()
-        .fold((0, base.to_path_buf()), |(mut depth, mut sum), next| {
-            match next {
-                Component::Normal(p) => {
-                    sum.push(p);
-                    depth += 1;
-                },
-                Component::ParentDir if depth > 0 => if sum.pop() {
-                    depth -= 1;
-                },
-                _ => ()
-            };
-            (depth, sum)
-        });
-    path
-}
-
 pub fn path_open(path: &Path) -> io::Result<fs::File> {
     let mut open_options = fs::File::options();
-    open_options.write(true).append(true).create_new(true);
+    open_options.write(true).create_new(true);
 
     match open_options.open(path) {
         Ok(fd) => Ok(fd),
@@ -127,3 +120,79 @@ pub fn path_open(path: &Path) -> io::Result<fs::File> {
         }
     }
 }
+
+/// A file being written, removed again if it's dropped before it's moved into place.
+pub struct PartialFile<'a> {
+    pub fd: fs::File,
+    path: &'a Path,
+    persisted: bool,
+}
+
+impl<'a> PartialFile<'a> {
+    /// create a new file at `path`, see `path_open`. a file already there is taken
+    /// for one left behind by a run that was killed, and replaced.
+    pub fn create(path: &'a Path) -> io::Result<PartialFile<'a>> {
+        match fs::remove_file(path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+            _ => ()
+        }
+        let fd = path_open(path)?;
+        Ok(PartialFile { fd, path, persisted: false })
+    }
+
+    /// close the file and rename it to `dest`, replacing what's there.
+    pub fn persist(mut self, dest: &Path) -> io::Result<()> {
+        fs::rename(self.path, dest)?;
+        self.persisted = true;
+        Ok(())
+    }
+}
+
+impl Drop for PartialFile<'_> {
+    fn drop(&mut self) {
+        if !self.persisted {
+            let _ = fs::remove_file(self.path);
+        }
+    }
+}
+
+/// copy `len` bytes at `offset` of `src` to the current position of `dst` in the kernel,
+/// which shares the blocks on filesystems with reflinks.
+///
+/// `Ok(false)` if the filesystems don't support it and nothing was copied.
+#[cfg(target_os = "linux")]
+pub fn copy_file_range(src: &fs::File, offset: u64, dst: &fs::File, len: u64) -> io::Result<bool> {
+    use std::os::unix::io::AsRawFd;
+
+    let mut off_in: libc::loff_t = offset.try_into()
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "offset overflow"))?;
+    let mut copied = 0;
+
+    while copied < len {
+        let chunk = (len - copied).min(1 << 30) as usize;
+        let n = unsafe {
+            libc::copy_file_range(src.as_raw_fd(), &mut off_in, dst.as_raw_fd(), std::ptr::null_mut(), chunk, 0)
+        };
+
+        if n < 0 {
+            let err = io::Error::last_os_error();
+            match err.raw_os_error() {
+                Some(libc::EINTR) => continue,
+                Some(libc::ENOSYS | libc::EXDEV | libc::EOPNOTSUPP | libc::EINVAL | libc::EPERM)
+                    if copied == 0 => return Ok(false),
+                _ => return Err(err)
+            }
+        }
+        if n == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into())
+        }
+        copied += n as u64;
+    }
+
+    Ok(true)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn copy_file_range(_src: &fs::File, _offset: u64, _dst: &fs::File, _len: u64) -> io::Result<bool> {
+    Ok(false)
+}
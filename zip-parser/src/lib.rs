@@ -18,6 +18,18 @@ pub mod system {
     pub const UNIX: u16 = 3;
 }
 
+pub mod flag {
+    /// 4.4.4 bit 11: Language encoding flag (EFS), filename and comment are UTF-8.
+    pub const EFS: u16 = 1 << 11;
+}
+
+pub mod extra {
+    /// 4.6.9 Info-ZIP Unicode Path Extra Field
+    pub const UNICODE_PATH: u16 = 0x7075;
+    /// 4.6.8 Info-ZIP Unicode Comment Extra Field
+    pub const UNICODE_COMMENT: u16 = 0x6375;
+}
+
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("eof")]
@@ -264,6 +276,12 @@ pub struct CentralFileHeader<'a> {
     pub comment: &'a [u8]
 }
 
+impl<'a> CentralFileHeader<'a> {
+    pub fn extra_fields(&self) -> ExtraFields<'a> {
+        ExtraFields { buf: self.extra }
+    }
+}
+
 impl CentralFileHeader<'_> {
     const SIGNATURE: &[u8; 4] = &[b'P', b'K', 1, 2];
 
@@ -316,6 +334,84 @@ impl CentralFileHeader<'_> {
     }
 }
 
+/*
+ * 4.5.1 extra field
+ *
+ *  header1+data1 + header2+data2 . . .
+ *
+ *  Header ID - 2 bytes
+ *  Data Size - 2 bytes
+ */
+
+#[non_exhaustive]
+#[derive(Debug)]
+pub struct ExtraField<'a> {
+    pub id: u16,
+    pub data: &'a [u8]
+}
+
+pub struct ExtraFields<'a> {
+    buf: &'a [u8]
+}
+
+impl<'a> Iterator for ExtraFields<'a> {
+    type Item = Result<ExtraField<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buf.is_empty() {
+            return None
+        }
+
+        let parse = |buf| -> Result<_, Error> {
+            let (buf, id) = read_u16(buf)?;
+            let (buf, size) = read_u16(buf)?;
+            let (buf, data) = take(buf, size.into())?;
+            Ok((buf, ExtraField { id, data }))
+        };
+
+        match parse(self.buf) {
+            Ok((buf, field)) => {
+                self.buf = buf;
+                Some(Ok(field))
+            },
+            Err(err) => {
+                self.buf = &[];
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/*
+ * 4.6.9 Info-ZIP Unicode Path Extra Field (0x7075), and
+ * 4.6.8 Info-ZIP Unicode Comment Extra Field (0x6375)
+ *
+ *  Version       1 byte      version of this extra field, currently 1
+ *  NameCRC32     4 bytes     File Name Field CRC32 Checksum
+ *  UnicodeName   Variable    UTF-8 version of the entry File Name
+ */
+
+#[non_exhaustive]
+#[derive(Debug)]
+pub struct UnicodeExtraField<'a> {
+    pub version: u8,
+    pub crc32: u32,
+    pub text: &'a [u8]
+}
+
+impl UnicodeExtraField<'_> {
+    pub fn parse(buf: &[u8]) -> Result<UnicodeExtraField<'_>, Error> {
+        let (buf, version) = take(buf, 1)?;
+        let (text, crc32) = read_u32(buf)?;
+
+        Ok(UnicodeExtraField {
+            version: version[0],
+            crc32,
+            text
+        })
+    }
+}
+
 #[non_exhaustive]
 #[derive(Debug)]
 pub struct LocalFileHeader<'a> {
@@ -433,7 +529,11 @@ impl<'a> Iterator for ZipEntries<'a> {
         let input = self.buf;
         let (input, cfh) = match CentralFileHeader::parse(input) {
             Ok(output) => output,
-            Err(err) => return Some(Err(err))
+            Err(err) => {
+                // the rest of the central directory can't be located, stop here
+                self.count = 0;
+                return Some(Err(err))
+            }
         };
 
         self.buf = input;
@@ -452,7 +552,6 @@ pub struct Zip64Archive<'a> {
 impl Zip64Archive<'_> {
     pub fn parse(buf: &[u8]) -> Result<Zip64Archive<'_>, Error> {
         let (eocdr_offset, eocdr) = EocdRecord::find(&buf)?;
-        println!("{:?}", eocdr);
 
         if eocdr.disk_nbr != 0
             || eocdr.cd_start_disk != 0
@@ -462,14 +561,12 @@ impl Zip64Archive<'_> {
         }
 
         let zip64_eocdl = Zip64EocdLocator::find(&buf, eocdr_offset)?;
-        println!("{:?}", zip64_eocdl);
 
         let zip64_eocdr_offset: usize = zip64_eocdl.offset
             .try_into()
             .map_err(|_| Error::TODO)?;
 
         let (_, zip64_eocdr) = Zip64EocdRecord::parse(&buf[zip64_eocdr_offset..])?;
-        println!("{:?}", zip64_eocdr);
 
         Ok(Zip64Archive { buf, eocdr, zip64_eocdr })
     }
@@ -478,6 +575,10 @@ impl Zip64Archive<'_> {
         &self.eocdr
     }
 
+    pub fn zip64_eocdr(&self) -> &Zip64EocdRecord<'_> {
+        &self.zip64_eocdr
+    }
+
     pub fn entries(&self) -> Result<Zip64Entries<'_>, Error> {
         let offset: usize = self.zip64_eocdr.cd_offset
             .try_into()
@@ -527,7 +628,11 @@ impl<'a> Iterator for Zip64Entries<'a> {
         let input = self.buf;
         let (input, cfh) = match CentralFileHeader::parse(input) {
             Ok(output) => output,
-            Err(err) => return Some(Err(err))
+            Err(err) => {
+                // the rest of the central directory can't be located, stop here
+                self.count = 0;
+                return Some(Err(err))
+            }
         };
 
         self.buf = input;
@@ -536,3 +641,47 @@ impl<'a> Iterator for Zip64Entries<'a> {
         Some(Ok(cfh))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extra_fields() {
+        let buf = [
+            0x75, 0x70, 3, 0, b'a', b'b', b'c',
+            0x01, 0x00, 0, 0,
+        ];
+        let fields = ExtraFields { buf: &buf }.collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(fields.len(), 2);
+        assert_eq!(fields[0].id, extra::UNICODE_PATH);
+        assert_eq!(fields[0].data, b"abc");
+        assert_eq!(fields[1].id, 1);
+        assert!(fields[1].data.is_empty());
+    }
+
+    #[test]
+    fn truncated_extra_field() {
+        // the declared size runs past the end
+        let buf = [0x75, 0x70, 9, 0, b'a', 0x01, 0x00];
+        let mut fields = ExtraFields { buf: &buf };
+        assert!(matches!(fields.next(), Some(Err(Error::Eof))));
+        assert!(fields.next().is_none());
+
+        let mut fields = ExtraFields { buf: &[0x75] };
+        assert!(matches!(fields.next(), Some(Err(Error::Eof))));
+        assert!(fields.next().is_none());
+    }
+
+    #[test]
+    fn unicode_extra_field() {
+        let buf = [1, 0x78, 0x56, 0x34, 0x12, 0xe6, 0x96, 0x87];
+        let field = UnicodeExtraField::parse(&buf).unwrap();
+        assert_eq!(field.version, 1);
+        assert_eq!(field.crc32, 0x12345678);
+        assert_eq!(field.text, "文".as_bytes());
+
+        assert!(UnicodeExtraField::parse(&[1, 0, 0, 0]).is_err());
+        assert!(UnicodeExtraField::parse(&[1, 0, 0, 0, 0]).unwrap().text.is_empty());
+    }
+}